@@ -0,0 +1,560 @@
+use super::delay;
+use super::number;
+use super::parser::{selector, Number, AST};
+use super::reducer::{self, Reducer, Value};
+use super::reduction::{self, Reduction};
+use super::scope::Table;
+use super::stream::{self, Combinator, Source};
+use super::tokenizer::Name;
+use super::typer::{self, symbol, Type};
+use std::fmt;
+
+// Each point of a checked program compiles to an instruction vector of
+// its own, run on a stack. The points it reads are its input slots,
+// loaded as the instructions reach them, so a point runs independently
+// of how the others were computed. Replicating a graph, or calling a
+// function-like one, `f{x: 1}`, loads the graph and gives it the
+// arguments, its points running their own code in the frame of the
+// call. Expressions the instructions cannot express yet, graph
+// literals, variants, overloaded operators and delays, leave their
+// point to the tree walking reducer, and the disassembly lists those
+// points as not compiled.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+	Push(Value),  // a constant
+	Load(usize),  // the value of an input slot
+	Array(usize), // the last n values as an array
+	Tuple(usize), // the last n values as a tuple
+	Unary(Name),
+	Binary(Name),
+	Select(String),  // a labeled point of a graph or variant
+	Coalesce(usize), // jump unless Nothing, else drop it
+	Pattern,         // a value checked against a type
+	Convert(Number),
+	Reduce(Reduction, usize), // with the last n values as arguments
+	Source(Source, usize),    // with the last n values as arguments
+	Combinator(Combinator, usize), // with the last n values as arguments
+	// the graph below the arguments given the last values by label, or
+	// one value taken apart
+	Replicate(Option<Vec<String>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Code {
+	pub inputs: Vec<usize>, // slot -> point read
+	pub instructions: Vec<Instruction>,
+}
+
+// per point, the code computing it, if its expression compiles
+pub fn compile(table: &Table, types: &[Type]) -> Vec<Option<Code>> {
+	(0..table.points.len())
+		.map(|id| {
+			let point = &table.points[id];
+			// points the reducer gives a value without an expression
+			let taken = point.binding.is_some()
+				|| table.destructures(id).is_some();
+			if taken
+				|| matches!(point.value, AST::Nothing)
+				|| matches!(types[id], Type::Type(_))
+			{
+				return None;
+			}
+			let mut compiler = Compiler {
+				table,
+				at: &point.path,
+				inputs: vec![],
+				instructions: vec![],
+			};
+			compiler.expression(point.scope, point.value)?;
+			Some(Code {
+				inputs: compiler.inputs,
+				instructions: compiler.instructions,
+			})
+		})
+		.collect()
+}
+
+struct Compiler<'t, 'a> {
+	table: &'t Table<'a>,
+	at: &'t str, // path of the point compiled
+	inputs: Vec<usize>,
+	instructions: Vec<Instruction>,
+}
+
+impl Compiler<'_, '_> {
+	fn expression(
+		&mut self,
+		scope: Option<usize>,
+		ast: &AST,
+	) -> Option<()> {
+		if !matches!(
+			delay::delay(self.table, scope, ast, self.at),
+			Ok(None)
+		) {
+			return None;
+		}
+		match ast {
+			AST::Nothing => self.emit(Instruction::Push(Value::Nothing)),
+			AST::Number(n, text) => {
+				// a literal in error is reported by the reducer
+				let v = reducer::number(*n, text).ok()?;
+				self.emit(Instruction::Push(v))
+			}
+			AST::String(text) => self.emit(Instruction::Push(
+				Value::String(text.trim_matches('"').to_string()),
+			)),
+			AST::Array(list) => {
+				self.list(scope, list)?;
+				self.emit(Instruction::Array(list.len()))
+			}
+			AST::Tuple(list) => {
+				self.list(scope, list)?;
+				self.emit(Instruction::Tuple(list.len()))
+			}
+			AST::Point(_, _, _, value) | AST::Arg(value) => {
+				self.expression(scope, value)
+			}
+			AST::Ref(name) => match self.table.resolve(scope, name) {
+				Some(id) => self.load(id),
+				None => {
+					let t = typer::builtin(name)?;
+					self.emit(Instruction::Push(Value::Type(t)))
+				}
+			},
+			AST::Op2(Name::Select | Name::Optional, left, right) => {
+				let label = selector(right)?;
+				// a selection on Nothing is Nothing, so `?.` is `.`
+				self.expression(scope, left)?;
+				self.emit(Instruction::Select(label.to_string()))
+			}
+			AST::Op2(Name::Coalesce, left, right) => {
+				self.expression(scope, left)?;
+				let jump = self.instructions.len();
+				self.emit(Instruction::Coalesce(0))?;
+				self.expression(scope, right)?;
+				self.instructions[jump] =
+					Instruction::Coalesce(self.instructions.len());
+				Some(())
+			}
+			AST::Op2(Name::Pattern, left, right) => {
+				// the cases of a union value are left to the reducer
+				if matches!(**right, AST::Graph(_)) {
+					return None;
+				}
+				match number::range(left) {
+					Some((n, lo, hi)) => self.emit(Instruction::Push(
+						Value::Type(Type::Range(n, lo, hi)),
+					))?,
+					None => self.expression(scope, left)?,
+				}
+				self.expression(scope, right)?;
+				self.emit(Instruction::Pattern)
+			}
+			// an operator with keys in scope may be overloaded on graphs
+			AST::Op2(op, _, _) | AST::Op1(op, _) if self.overloaded(scope, *op) => {
+				None
+			}
+			AST::Op2(op, left, right) => {
+				self.expression(scope, left)?;
+				self.expression(scope, right)?;
+				self.emit(Instruction::Binary(*op))
+			}
+			AST::Op1(op, value) => {
+				self.expression(scope, value)?;
+				self.emit(Instruction::Unary(*op))
+			}
+			AST::Rep(original, argument) => {
+				let name = match &**original {
+					AST::Ref(name)
+						if self.table.resolve(scope, name).is_none() =>
+					{
+						name
+					}
+					_ => return self.replicate(scope, original, argument),
+				};
+				if self.table.variant(scope, name).is_some() {
+					return None;
+				}
+				if let Some(n) = number::from_name(name) {
+					self.expression(scope, argument)?;
+					return self.emit(Instruction::Convert(n));
+				}
+				if let Some(r) = reduction::from_name(name) {
+					let count = self.builtin(
+						scope,
+						argument,
+						reduction::name(r),
+						reduction::parameters(r),
+						reduction::required(r),
+					)?;
+					return self.emit(Instruction::Reduce(r, count));
+				}
+				if let Some(s) = stream::from_name(name) {
+					let params = stream::parameters(s);
+					let count = self.builtin(
+						scope,
+						argument,
+						stream::name(s),
+						params,
+						params.len(),
+					)?;
+					return self.emit(Instruction::Source(s, count));
+				}
+				if let Some(c) = stream::combinator_from_name(name) {
+					let params = stream::combinator_parameters(c);
+					let name = stream::combinator_name(c);
+					let count = self.builtin(
+						scope,
+						argument,
+						name,
+						params,
+						params.len(),
+					)?;
+					return self.emit(Instruction::Combinator(c, count));
+				}
+				None
+			}
+			AST::Graph(_) => None,
+		}
+	}
+
+	fn list(&mut self, scope: Option<usize>, list: &[AST]) -> Option<()> {
+		for item in list {
+			self.expression(scope, item)?;
+		}
+		Some(())
+	}
+
+	// the arguments of a built-in in parameter order, the points of a
+	// graph or one value, and how many were given
+	fn builtin(
+		&mut self,
+		scope: Option<usize>,
+		argument: &AST,
+		name: &str,
+		params: &[&str],
+		required: usize,
+	) -> Option<usize> {
+		// a point of the graph, or None for the one value
+		let args: Vec<(String, Option<usize>)> = match argument {
+			AST::Graph(_) => {
+				let graph = self.table.graph(argument)?;
+				self.table.scopes[graph]
+					.points
+					.iter()
+					.map(|id| {
+						(self.table.points[*id].label.clone(), Some(*id))
+					})
+					.collect()
+			}
+			_ => vec![(String::new(), None)],
+		};
+		// misplaced arguments are reported by the reducer
+		let args = reduction::optional_arguments(
+			name, params, required, args, self.at,
+		)
+		.ok()?;
+		let mut count = 0;
+		for arg in args.into_iter().flatten() {
+			match arg {
+				Some(id) => self.load(id)?,
+				None => self.expression(scope, argument)?,
+			}
+			count += 1;
+		}
+		Some(count)
+	}
+
+	// the graph replicated, then the points of a graph argument with
+	// their labels, or the one value
+	fn replicate(
+		&mut self,
+		scope: Option<usize>,
+		original: &AST,
+		argument: &AST,
+	) -> Option<()> {
+		self.expression(scope, original)?;
+		let labels = match argument {
+			AST::Graph(_) => {
+				let table = self.table;
+				let graph = table.graph(argument)?;
+				let mut labels = vec![];
+				for id in &table.scopes[graph].points {
+					self.load(*id)?;
+					labels.push(table.points[*id].label.clone());
+				}
+				Some(labels)
+			}
+			_ => {
+				self.expression(scope, argument)?;
+				None
+			}
+		};
+		self.emit(Instruction::Replicate(labels))
+	}
+
+	// reading a point, through the slot it was given first
+	fn load(&mut self, id: usize) -> Option<()> {
+		let slot = match self.inputs.iter().position(|input| *input == id)
+		{
+			Some(slot) => slot,
+			None => {
+				self.inputs.push(id);
+				self.inputs.len() - 1
+			}
+		};
+		self.emit(Instruction::Load(slot))
+	}
+
+	fn overloaded(&self, scope: Option<usize>, op: Name) -> bool {
+		!self.table.candidates(scope, symbol(op)).is_empty()
+	}
+
+	fn emit(&mut self, instruction: Instruction) -> Option<()> {
+		self.instructions.push(instruction);
+		Some(())
+	}
+}
+
+// runs the code of a point, loading its inputs from the reducer
+pub fn run(r: &mut Reducer, code: &Code) -> Result<Value, String> {
+	let mut stack: Vec<Value> = vec![];
+	let mut pc = 0;
+	while let Some(instruction) = code.instructions.get(pc) {
+		pc += 1;
+		let v = match instruction {
+			Instruction::Push(v) => v.clone(),
+			Instruction::Load(slot) => r.point(code.inputs[*slot])?,
+			Instruction::Array(n) => Value::Array(last(&mut stack, *n)),
+			Instruction::Tuple(n) => Value::Tuple(last(&mut stack, *n)),
+			Instruction::Unary(op) => r.unary(*op, &pop(&mut stack))?,
+			Instruction::Binary(op) => {
+				let right = pop(&mut stack);
+				r.binary(*op, &pop(&mut stack), &right)?
+			}
+			Instruction::Select(label) => {
+				r.selected(&pop(&mut stack), label)?
+			}
+			Instruction::Coalesce(end) => {
+				if stack.last() != Some(&Value::Nothing) {
+					pc = *end;
+				} else {
+					stack.pop();
+				}
+				continue;
+			}
+			Instruction::Pattern => {
+				let v = pop(&mut stack);
+				match pop(&mut stack) {
+					Value::Type(t) => {
+						reducer::within(&t, &v).map_err(|msg| {
+							format!("{} in {}", msg, r.at())
+						})?;
+						reducer::cast(&t, v)
+					}
+					_ => v,
+				}
+			}
+			Instruction::Convert(n) => r.convert(*n, &pop(&mut stack))?,
+			Instruction::Reduce(reduction, n) => {
+				r.reduced(*reduction, last(&mut stack, *n))?
+			}
+			Instruction::Source(s, n) => {
+				match stream::source(*s, last(&mut stack, *n)) {
+					Ok(stream) => Value::Stream(stream),
+					Err(msg) => {
+						return Err(format!("{} in {}", msg, r.at()))
+					}
+				}
+			}
+			Instruction::Combinator(c, n) => {
+				match stream::combinator(*c, last(&mut stack, *n)) {
+					Ok(Some(stream)) => Value::Stream(stream),
+					Ok(None) => Value::Nothing,
+					Err(msg) => {
+						return Err(format!("{} in {}", msg, r.at()))
+					}
+				}
+			}
+			Instruction::Replicate(labels) => {
+				let args = match labels {
+					Some(labels) => labels
+						.iter()
+						.cloned()
+						.zip(last(&mut stack, labels.len()))
+						.collect(),
+					None => reducer::spread(pop(&mut stack)),
+				};
+				r.replicated(pop(&mut stack), args)?
+			}
+		};
+		stack.push(v);
+	}
+	Ok(pop(&mut stack))
+}
+
+fn pop(stack: &mut Vec<Value>) -> Value {
+	stack.pop().unwrap_or(Value::Nothing)
+}
+
+fn last(stack: &mut Vec<Value>, n: usize) -> Vec<Value> {
+	stack.split_off(stack.len().saturating_sub(n))
+}
+
+// the code of every compiled point, `path <- inputs` and then its
+// instructions, for debugging
+pub fn disassemble(table: &Table, code: &[Option<Code>]) -> String {
+	let mut output = String::new();
+	for (id, code) in code.iter().enumerate() {
+		let point = &table.points[id];
+		let code = match code {
+			Some(code) => code,
+			// graphs and parameters hold no expression to compile
+			None if matches!(point.value, AST::Graph(_) | AST::Nothing) => {
+				continue
+			}
+			None => {
+				output.push_str(&format!("{} not compiled\n", point.path));
+				continue;
+			}
+		};
+		let inputs: Vec<&str> = code
+			.inputs
+			.iter()
+			.map(|input| table.points[*input].path.as_str())
+			.collect();
+		output.push_str(&format!(
+			"{} <- {}\n",
+			point.path,
+			inputs.join(", ")
+		));
+		for (i, instruction) in code.instructions.iter().enumerate() {
+			output.push_str(&format!("\t{:<4} {}", i, instruction));
+			if let Instruction::Load(slot) = instruction {
+				output.push_str(&format!("\t; {}", inputs[*slot]));
+			}
+			output.push('\n');
+		}
+	}
+	output
+}
+
+impl fmt::Display for Instruction {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Instruction::Push(v) => write!(f, "push {}", v),
+			Instruction::Load(slot) => write!(f, "load {}", slot),
+			Instruction::Array(n) => write!(f, "array {}", n),
+			Instruction::Tuple(n) => write!(f, "tuple {}", n),
+			Instruction::Unary(op) => write!(f, "unary {}", symbol(*op)),
+			Instruction::Binary(op) => write!(f, "binary {}", symbol(*op)),
+			Instruction::Select(label) => write!(f, "select {}", label),
+			Instruction::Coalesce(end) => write!(f, "coalesce {}", end),
+			Instruction::Pattern => write!(f, "pattern"),
+			Instruction::Convert(n) => {
+				write!(f, "convert {}", number::name(*n))
+			}
+			Instruction::Reduce(r, n) => {
+				write!(f, "reduce {} {}", reduction::name(*r), n)
+			}
+			Instruction::Source(s, n) => {
+				write!(f, "source {} {}", stream::name(*s), n)
+			}
+			Instruction::Combinator(c, n) => {
+				write!(
+					f,
+					"combinator {} {}",
+					stream::combinator_name(*c),
+					n
+				)
+			}
+			Instruction::Replicate(Some(labels)) => {
+				// unlabeled arguments are given by position
+				let labels: Vec<&str> = labels
+					.iter()
+					.map(|l| if l.is_empty() { "_" } else { l })
+					.collect();
+				write!(f, "replicate {}", labels.join(", "))
+			}
+			Instruction::Replicate(None) => write!(f, "replicate"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compiler::{parser, tokenizer};
+	use std::rc::Rc;
+
+	// the value of every point, or the error computing it, run on the
+	// instructions compiled and on the tree walking reducer alone
+	fn both(source: &str) -> (Vec<String>, Vec<String>, usize) {
+		let ast = parser::parser(&tokenizer::tokenizer(source)).unwrap();
+		let mut compiled = Reducer::new(&ast).unwrap();
+		let mut walked = Reducer::new(&ast).unwrap();
+		walked.code = Rc::new(vec![None; walked.code.len()]);
+		let count = compiled.code.iter().flatten().count();
+		let values = |r: &mut Reducer| {
+			(0..r.types.len())
+				.map(|id| match r.point(id) {
+					Ok(v) => v.to_string(),
+					Err(msg) => msg,
+				})
+				.collect::<Vec<String>>()
+		};
+		(values(&mut compiled), values(&mut walked), count)
+	}
+
+	#[test]
+	fn instructions_compute_what_the_tree_does() {
+		let sources = [
+			"a: 2\nb: a * 3 + 1\nc: b ^ 2 - a / 2\nd: -c",
+			"a: [1, 2, 3]\nb: a * 2\nc: #b\nd: zip{a, b}",
+			"a: 1.5\nb: a > 1 & !(a = 2)\nc: u8(300)\nd: a ?? 0",
+			"a: [3, 1, 2]\nb: sort{a}\nc: sum{map{a, {x: -> x * x}}}",
+			"p: {x: 1, y: 2}\nq: p.x + p.y\nr: 10 / (q - 3)",
+			"a: 0..4\nb: filter{a, {x: -> x > 1}}\nc: u8 ~ 200",
+		];
+		for source in sources {
+			let (compiled, walked, count) = both(source);
+			assert!(count > 0, "nothing compiled in {:?}", source);
+			assert_eq!(compiled, walked, "{:?}", source);
+		}
+	}
+
+	#[test]
+	fn calls_load_the_graph_and_its_arguments() {
+		let source = "f: {x: 0, -> x + 1}\ny: f{x: 2}\nz: y * 2";
+		let ast = parser::parser(&tokenizer::tokenizer(source)).unwrap();
+		let r = Reducer::new(&ast).unwrap();
+		let code = disassemble(&r.deps.table, &r.code);
+		assert!(code.contains("y <- f, y.x"), "{}", code);
+		assert!(code.contains("replicate x"), "{}", code);
+		assert!(!code.contains("not compiled"), "{}", code);
+	}
+
+	#[test]
+	fn each_point_of_a_call_runs_its_own_code() {
+		let source = "quad: {a: 1, b: 0, c: 0
+			discriminant: b ^ 2 - 4 * a * c
+			-> (-b + discriminant ^ 0.5) / (2 * a)
+		}
+		root: quad{a: 1, b: -3, c: 2}
+		twice: double(root)
+		double: {x: -> x * 2}";
+		let ast = parser::parser(&tokenizer::tokenizer(source)).unwrap();
+		let r = Reducer::new(&ast).unwrap();
+		// every point holding an expression has a vector of its own
+		for (id, point) in r.deps.table.points.iter().enumerate() {
+			if !matches!(point.value, AST::Graph(_) | AST::Nothing) {
+				assert!(r.code[id].is_some(), "{}", point.path);
+			}
+		}
+		let (compiled, walked, _) = both(source);
+		assert_eq!(compiled, walked);
+		assert!(compiled.contains(&"2.0".to_string()), "{:?}", compiled);
+		assert!(compiled.contains(&"4.0".to_string()), "{:?}", compiled);
+	}
+}
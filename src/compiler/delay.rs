@@ -0,0 +1,62 @@
+use super::parser::AST;
+use super::reduction;
+use super::scope::Table;
+use super::tokenizer::Name;
+
+// `prev{count, 0}` is the value the point `count` had the tick before,
+// or 0 until it had one; `<count` is the same with Nothing until then.
+// Each change the reactor propagates is a tick, and a tick moves
+// every prev on, those of points the change does not reach too, so
+// `updates: prev{updates, 0} + 1` counts the changes to any input.
+// As the value comes from the tick before, a point may read itself
+// through prev, or through a point reading it, as in
+// `count: prev{count, 0} + clicks`; those are the only cycles allowed.
+
+pub const NAME: &str = "prev";
+pub const PARAMETERS: &[&str] = &["value", "initial"];
+
+pub struct Delay<'a> {
+	pub scope: Option<usize>, // scope of the value and the initial value
+	pub value: &'a AST,
+	pub initial: Option<&'a AST>,
+}
+
+// the delay an expression is, if it is one; `at` is the path of the
+// point holding it, for error messages
+pub fn delay<'a>(
+	table: &Table,
+	scope: Option<usize>,
+	ast: &'a AST,
+	at: &str,
+) -> Result<Option<Delay<'a>>, String> {
+	match ast {
+		AST::Op1(Name::Lt, value) => Ok(Some(Delay {
+			scope,
+			value,
+			initial: None,
+		})),
+		AST::Rep(original, argument) => match &**original {
+			AST::Ref(name)
+				if name == NAME && table.resolve(scope, name).is_none() =>
+			{
+				let (scope, args) = reduction::expressions(
+					table, scope, argument, NAME, PARAMETERS, at,
+				)?;
+				Ok(Some(Delay {
+					scope,
+					value: args[0],
+					initial: Some(args[1]),
+				}))
+			}
+			_ => Ok(None),
+		},
+		_ => Ok(None),
+	}
+}
+
+// the point a delay reads the value of, the value must name one
+pub fn target(table: &Table, delay: &Delay, at: &str) -> Result<usize, String> {
+	table
+		.target(delay.scope, delay.value)
+		.ok_or(format!("InvalidDelay: {} of an expression in {}", NAME, at))
+}
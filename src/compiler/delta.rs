@@ -0,0 +1,77 @@
+use super::reducer::Value;
+
+// Changes to the items of an array, given to the reactor in place of a
+// whole new array so the points computed from it by `map`, `filter`,
+// `fold`, `sum` and `sort` follow the changes rather than start over.
+// Indices are those of the array as the changes before left it.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+	Insert(usize, Value),
+	Remove(usize),
+	Update(usize, Value),
+}
+
+// a change made, with the item it took out
+#[derive(Debug, Clone, PartialEq)]
+pub enum Applied {
+	Insert(usize, Value),
+	Remove(usize, Value),
+	Update(usize, Value, Value), // index, item before, item after
+}
+
+// makes the changes in order, none of them if one is out of bounds
+pub fn apply(
+	items: &mut Vec<Value>,
+	changes: Vec<Change>,
+) -> Result<Vec<Applied>, String> {
+	let mut applied = vec![];
+	for change in changes {
+		let (i, bound) = match &change {
+			Change::Insert(i, _) => (*i, items.len() + 1),
+			Change::Remove(i) | Change::Update(i, _) => (*i, items.len()),
+		};
+		if i >= bound {
+			undo(items, &applied);
+			return Err(format!("IndexOutOfBounds: {} of {}", i, items.len()));
+		}
+		applied.push(match change {
+			Change::Insert(i, v) => {
+				items.insert(i, v.clone());
+				Applied::Insert(i, v)
+			}
+			Change::Remove(i) => Applied::Remove(i, items.remove(i)),
+			Change::Update(i, v) => {
+				let old = std::mem::replace(&mut items[i], v.clone());
+				Applied::Update(i, old, v)
+			}
+		});
+	}
+	Ok(applied)
+}
+
+// makes changes already made to an equal array
+pub fn redo(items: &mut Vec<Value>, applied: &[Applied]) {
+	for change in applied {
+		match change {
+			Applied::Insert(i, v) => items.insert(*i, v.clone()),
+			Applied::Remove(i, _) => {
+				items.remove(*i);
+			}
+			Applied::Update(i, _, v) => items[*i] = v.clone(),
+		}
+	}
+}
+
+// takes changes back, the last first
+pub fn undo(items: &mut Vec<Value>, applied: &[Applied]) {
+	for change in applied.iter().rev() {
+		match change {
+			Applied::Insert(i, _) => {
+				items.remove(*i);
+			}
+			Applied::Remove(i, v) => items.insert(*i, v.clone()),
+			Applied::Update(i, v, _) => items[*i] = v.clone(),
+		}
+	}
+}
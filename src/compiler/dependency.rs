@@ -0,0 +1,315 @@
+use super::delay;
+use super::parser::{is_type, is_type_name, variants, AST};
+use super::scope::{scope, Table};
+use super::tokenizer::Name;
+
+// Point level dependency graph. A point depends on every point its
+// expression references and on the points of any graph literal it
+// holds, so a graph is only complete once its children are.
+// References that cannot be resolved statically are left to the typer.
+// A point reading another through `prev` reads the value it had the
+// tick before, so that read is kept apart and may close a cycle.
+
+pub struct Dependencies<'a> {
+	pub table: Table<'a>,
+	pub edges: Vec<Vec<usize>>, // point -> points it reads
+	pub delayed: Vec<Vec<usize>>, // point -> points it reads through prev
+	pub order: Vec<usize>,      // dependencies before dependents
+}
+
+pub fn dependencies(ast: &AST) -> Result<Dependencies<'_>, String> {
+	let table = scope(ast)?;
+	let mut edges = vec![];
+	let mut delayed = vec![];
+	for point in &table.points {
+		let mut reads = Reads {
+			now: vec![],
+			later: vec![],
+			at: &point.path,
+		};
+		match variants(point.value) {
+			// variant names refer back to the union declaring them
+			Some(cases) if is_type_name(&point.label) => {
+				for payload in cases.into_iter().filter_map(|(_, p)| p) {
+					table.reads(point.scope, payload, &mut reads)?;
+				}
+			}
+			_ if table.field(edges.len()).is_some() => {}
+			// names a pattern binds read the point taking a value apart,
+			// and that point reads only the value
+			_ if point.binding.is_some() => {
+				reads.now.extend(point.binding.as_ref().map(|(p, _)| *p))
+			}
+			_ => match table.destructures(edges.len()) {
+				Some((_, matched)) => {
+					table.reads(point.scope, matched, &mut reads)?
+				}
+				None => table.reads(point.scope, point.value, &mut reads)?,
+			},
+		}
+		edges.push(reads.now);
+		delayed.push(reads.later);
+	}
+
+	let mut walk = Walk {
+		edges: &edges,
+		state: vec![State::Unvisited; edges.len()],
+		stack: vec![],
+		order: vec![],
+		cycles: vec![],
+	};
+	for id in 0..edges.len() {
+		walk.visit(id);
+	}
+
+	if walk.cycles.is_empty() {
+		let order = walk.order;
+		Ok(Dependencies {
+			table,
+			edges,
+			delayed,
+			order,
+		})
+	} else {
+		Err(walk
+			.cycles
+			.iter()
+			.map(|cycle| {
+				let path: Vec<&str> = cycle
+					.iter()
+					.map(|id| &table.points[*id].path[..])
+					.collect();
+				format!("CyclicReference: {}", path.join(" -> "))
+			})
+			.collect::<Vec<String>>()
+			.join("\n"))
+	}
+}
+
+// the points an expression reads now and the tick before
+struct Reads<'p> {
+	now: Vec<usize>,
+	later: Vec<usize>,
+	at: &'p str, // path of the point reading them
+}
+
+impl Table<'_> {
+	fn reads(
+		&self,
+		scope: Option<usize>,
+		ast: &AST,
+		out: &mut Reads,
+	) -> Result<(), String> {
+		if let Some(d) = delay::delay(self, scope, ast, out.at)? {
+			push(&mut out.later, delay::target(self, &d, out.at)?);
+			if let Some(initial) = d.initial {
+				self.reads(d.scope, initial, out)?;
+			}
+			return Ok(());
+		}
+		match ast {
+			AST::Graph(_) => {
+				if let Some(graph) = self.graph(ast) {
+					for point in &self.scopes[graph].points {
+						push(&mut out.now, *point);
+					}
+				}
+			}
+			AST::Ref(name) => {
+				let point = self.target(scope, ast);
+				if let Some(point) = point.or(self.variant(scope, name)) {
+					push(&mut out.now, point);
+				}
+			}
+			AST::Op2(Name::Select | Name::Optional, left, right) => {
+				match self.target(scope, ast) {
+					Some(point) => {
+						push(&mut out.now, point);
+						// a typed parameter selects from the value it is
+						// given, not only from its declared type
+						if let Some(param) = self.parameter(scope, left) {
+							push(&mut out.now, param);
+						}
+					}
+					None => {
+						self.reads(scope, left, out)?;
+						if !matches!(**right, AST::Ref(_)) {
+							self.reads(scope, right, out)?;
+						}
+					}
+				}
+			}
+			AST::Array(list) | AST::Tuple(list) => {
+				for item in list {
+					self.reads(scope, item, out)?;
+				}
+			}
+			AST::Op2(_, left, right) | AST::Rep(left, right) => {
+				self.reads(scope, left, out)?;
+				self.reads(scope, right, out)?;
+			}
+			AST::Op1(_, value) | AST::Arg(value) => {
+				self.reads(scope, value, out)?
+			}
+			AST::Point(_, _, _, value) => self.reads(scope, value, out)?,
+			AST::Nothing | AST::Number(_, _) | AST::String(_) => {}
+		}
+		Ok(())
+	}
+
+	// the typed parameter a selection starts from, `a` in `a.x` with
+	// `a: Point`
+	fn parameter(&self, scope: Option<usize>, ast: &AST) -> Option<usize> {
+		match ast {
+			AST::Ref(name) => {
+				let id = self.resolve(scope, name)?;
+				let point = &self.points[id];
+				match point.value {
+					AST::Ref(t) if !is_type_name(&point.label) => {
+						let target = self.resolve(point.scope, t)?;
+						Some(id).filter(|_| self.declares_type(target))
+					}
+					_ => None,
+				}
+			}
+			AST::Op2(Name::Select | Name::Optional, left, _) => {
+				self.parameter(scope, left)
+			}
+			_ => None,
+		}
+	}
+
+	// a capitalized point declaring a type rather than holding a value,
+	// `Point: {x: f64}` or `Id: u32` but not `Pi: 3.14`
+	fn declares_type(&self, id: usize) -> bool {
+		let point = &self.points[id];
+		is_type_name(&point.label) && is_type(point.value)
+	}
+}
+
+fn push(out: &mut Vec<usize>, point: usize) {
+	if !out.contains(&point) {
+		out.push(point);
+	}
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+	Unvisited,
+	Visiting,
+	Done,
+}
+
+struct Walk<'e> {
+	edges: &'e Vec<Vec<usize>>,
+	state: Vec<State>,
+	stack: Vec<usize>,
+	order: Vec<usize>,
+	cycles: Vec<Vec<usize>>,
+}
+
+impl Walk<'_> {
+	// depth first, without recursing so a long chain of points cannot
+	// run out of stack
+	fn visit(&mut self, root: usize) {
+		if self.state[root] != State::Unvisited {
+			return;
+		}
+		let edges = self.edges;
+		self.state[root] = State::Visiting;
+		self.stack.push(root);
+		// per point on the stack, the next of its edges to follow
+		let mut next = vec![0];
+		while let Some(&i) = next.last() {
+			let id = self.stack[self.stack.len() - 1];
+			let read = match edges[id].get(i) {
+				Some(read) => *read,
+				None => {
+					next.pop();
+					self.stack.pop();
+					self.state[id] = State::Done;
+					self.order.push(id);
+					continue;
+				}
+			};
+			*next.last_mut().unwrap() += 1;
+			match self.state[read] {
+				State::Done => {}
+				State::Visiting => {
+					// back edge, the cycle is the stack from `read` onwards
+					let start =
+						self.stack.iter().position(|p| *p == read).unwrap();
+					let mut cycle = self.stack[start..].to_vec();
+					cycle.push(read);
+					self.cycles.push(cycle);
+				}
+				State::Unvisited => {
+					self.state[read] = State::Visiting;
+					self.stack.push(read);
+					next.push(0);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compiler::{parser, tokenizer};
+
+	#[test]
+	fn long_chain_does_not_overflow() {
+		// each point reads the next, so the walk starts at the far end
+		let mut source = String::new();
+		for i in 0..20_000 {
+			source.push_str(&format!("a{}: a{}\n", i, i + 1));
+		}
+		source.push_str("a20000: 1\n");
+		let ast = parser::parser(&tokenizer::tokenizer(&source)).unwrap();
+		// on a small stack, where visiting a point per call would not fit
+		let ordered = std::thread::scope(|s| {
+			std::thread::Builder::new()
+				.stack_size(256 * 1024)
+				.spawn_scoped(s, || {
+					let deps = dependencies(&ast).unwrap();
+					let at = |label: &str| {
+						let points = &deps.table.points;
+						let id = points.iter().position(|p| p.label == label);
+						deps.order.iter().position(|p| Some(*p) == id)
+					};
+					at("a20000") < at("a0")
+				})
+				.unwrap()
+				.join()
+				.unwrap()
+		});
+		assert!(ordered);
+	}
+
+	#[test]
+	fn only_type_declarations_make_parameters() {
+		let reads = |source: &str| {
+			let ast = parser::parser(&tokenizer::tokenizer(source)).unwrap();
+			let deps = dependencies(&ast).unwrap();
+			let points = &deps.table.points;
+			let c = points.iter().position(|p| p.label == "c").unwrap();
+			let reads: Vec<&str> =
+				deps.edges[c].iter().map(|id| &points[*id].label[..]).collect();
+			reads.join(" ")
+		};
+		// a parameter is given its value, a copy reads the original
+		let param = "Point: {x: f64}\nb: Point\nc: b.x";
+		assert_eq!(reads(param), "x b");
+		let copy = "Origin: {x: 0}\nb: Origin\nc: b.x\nPi: 3.14\nd: Pi";
+		assert_eq!(reads(copy), "x");
+	}
+
+	#[test]
+	fn cycles_are_reported() {
+		let source = "a: b\nb: c\nc: a\n";
+		let ast = parser::parser(&tokenizer::tokenizer(source)).unwrap();
+		let msg = dependencies(&ast).err().unwrap();
+		assert!(msg.starts_with("CyclicReference: "), "{}", msg);
+	}
+}
@@ -1,3 +1,13 @@
+pub mod bytecode;
+pub mod delay;
+pub mod delta;
+pub mod dependency;
+pub mod number;
 pub mod parser;
+pub mod reactor;
+pub mod reducer;
+pub mod reduction;
+pub mod scope;
+pub mod stream;
 pub mod tokenizer;
 pub mod typer;
@@ -0,0 +1,205 @@
+use super::parser::{Number, AST};
+use super::tokenizer::Name;
+
+// The numeric tower. Integer and Decimal are the types of untyped
+// literals and adapt to whatever typed number they meet; typed numbers
+// only widen when every value of one fits exactly in the other.
+
+pub fn name(n: Number) -> &'static str {
+	match n {
+		Number::Integer => "Integer",
+		Number::Decimal => "Decimal",
+		Number::Boolean => "Boolean",
+		Number::I8 => "i8",
+		Number::I16 => "i16",
+		Number::I32 => "i32",
+		Number::I64 => "i64",
+		Number::I128 => "i128",
+		Number::U8 => "u8",
+		Number::U16 => "u16",
+		Number::U32 => "u32",
+		Number::U64 => "u64",
+		Number::U128 => "u128",
+		Number::F32 => "f32",
+		Number::F64 => "f64",
+		Number::F128 => "f128",
+	}
+}
+
+// typed number names usable in source, e.g. `u16(reading)`
+pub fn from_name(name: &str) -> Option<Number> {
+	match name {
+		"i8" => Some(Number::I8),
+		"i16" => Some(Number::I16),
+		"i32" => Some(Number::I32),
+		"i64" => Some(Number::I64),
+		"i128" => Some(Number::I128),
+		"u8" => Some(Number::U8),
+		"u16" => Some(Number::U16),
+		"u32" => Some(Number::U32),
+		"u64" => Some(Number::U64),
+		"u128" => Some(Number::U128),
+		"f32" => Some(Number::F32),
+		"f64" => Some(Number::F64),
+		"f128" => Some(Number::F128),
+		_ => None,
+	}
+}
+
+// the value of a number literal, a negated one included
+pub fn literal(ast: &AST) -> Option<(Number, f64)> {
+	match ast {
+		AST::Number(Number::Boolean, _) => None,
+		AST::Number(n, text) => {
+			let value = text.replace('\'', "").parse().ok()?;
+			Some((*n, value))
+		}
+		AST::Op1(Name::Sub, value) => {
+			let (n, value) = literal(value)?;
+			Some((n, -value))
+		}
+		_ => None,
+	}
+}
+
+// the value of an integer literal, a negated one included
+pub fn integer(ast: &AST) -> Option<i128> {
+	match ast {
+		AST::Number(Number::Integer, text) => {
+			text.replace('\'', "").parse().ok()
+		}
+		AST::Op1(Name::Sub, value) => integer(value)?.checked_neg(),
+		_ => None,
+	}
+}
+
+// `0..100` written with literal bounds, as a type
+pub fn range(ast: &AST) -> Option<(Number, f64, f64)> {
+	match ast {
+		AST::Op2(Name::Range, lo, hi) => {
+			let (a, lo) = literal(lo)?;
+			let (b, hi) = literal(hi)?;
+			Some((join(a, b).ok()?, lo, hi))
+		}
+		_ => None,
+	}
+}
+
+#[derive(PartialEq)]
+enum Class {
+	Literal,
+	Signed,
+	Unsigned,
+	Float,
+	Boolean,
+}
+
+fn class(n: Number) -> Class {
+	match n {
+		Number::Integer | Number::Decimal => Class::Literal,
+		Number::Boolean => Class::Boolean,
+		Number::I8
+		| Number::I16
+		| Number::I32
+		| Number::I64
+		| Number::I128 => Class::Signed,
+		Number::U8
+		| Number::U16
+		| Number::U32
+		| Number::U64
+		| Number::U128 => Class::Unsigned,
+		Number::F32 | Number::F64 | Number::F128 => Class::Float,
+	}
+}
+
+// value bits for integers, mantissa bits for floats
+fn bits(n: Number) -> u32 {
+	match n {
+		Number::I8 | Number::U8 => 8,
+		Number::I16 | Number::U16 => 16,
+		Number::I32 | Number::U32 => 32,
+		Number::I64 | Number::U64 => 64,
+		Number::I128 | Number::U128 => 128,
+		Number::F32 => 24,
+		Number::F64 => 53,
+		Number::F128 => 113,
+		Number::Integer | Number::Decimal | Number::Boolean => 0,
+	}
+}
+
+// an integer as `n` holds it, wrapping like a cast
+pub fn wrap(n: Number, value: i128) -> i128 {
+	let bits = bits(n);
+	if bits == 0 || bits >= 128 || class(n) == Class::Float {
+		return value;
+	}
+	let unsigned = value & ((1 << bits) - 1);
+	if class(n) == Class::Signed && unsigned >> (bits - 1) == 1 {
+		unsigned - (1 << bits)
+	} else {
+		unsigned
+	}
+}
+
+// the type of an untyped literal, adapting to the number it meets
+pub fn is_literal(n: Number) -> bool {
+	class(n) == Class::Literal
+}
+
+// an integer is one `n` holds as it is
+pub fn holds(n: Number, value: i128) -> bool {
+	wrap(n, value) == value && !(class(n) == Class::Unsigned && value < 0)
+}
+
+pub fn is_float(n: Number) -> bool {
+	matches!(class(n), Class::Float) || n == Number::Decimal
+}
+
+// every value of `from` is exactly representable as `to`
+pub fn widens(from: Number, to: Number) -> bool {
+	if from == to {
+		return true;
+	}
+	match (class(from), class(to)) {
+		(Class::Boolean, _) | (_, Class::Boolean) => false,
+		(Class::Literal, Class::Literal) => {
+			from == Number::Integer && to == Number::Decimal
+		}
+		(Class::Literal, Class::Float) => true,
+		(Class::Literal, _) => from == Number::Integer,
+		(_, Class::Literal) => false,
+		(Class::Signed, Class::Signed)
+		| (Class::Unsigned, Class::Unsigned)
+		| (Class::Float, Class::Float) => bits(from) < bits(to),
+		(Class::Unsigned, Class::Signed) => bits(from) < bits(to),
+		(Class::Signed, Class::Unsigned) => false,
+		(Class::Signed, Class::Float) => bits(from) < bits(to),
+		(Class::Unsigned, Class::Float) => bits(from) < bits(to),
+		(Class::Float, _) => false,
+	}
+}
+
+// the type two numbers meet at, without losing values
+pub fn join(a: Number, b: Number) -> Result<Number, String> {
+	if widens(a, b) {
+		Ok(b)
+	} else if widens(b, a) {
+		Ok(a)
+	} else {
+		let signs = [class(a), class(b)];
+		if signs.contains(&Class::Signed) && signs.contains(&Class::Unsigned)
+		{
+			Err(format!(
+				"SignChangingConversion: {} and {} need an explicit conversion",
+				name(a),
+				name(b)
+			))
+		} else {
+			Err(format!(
+				"LossyConversion: {} and {} need an explicit conversion",
+				name(a),
+				name(b)
+			))
+		}
+	}
+}
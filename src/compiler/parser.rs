@@ -1,3 +1,4 @@
+use super::number;
 use super::tokenizer::{Kind, Name, Token};
 use std::cell::RefCell;
 
@@ -13,7 +14,7 @@ pub fn parser(tokens: &Vec<Token>) -> Result<AST, String> {
 	};
 	cursor.program()
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Number {
 	Integer,
 	Decimal,
@@ -33,6 +34,137 @@ pub enum Number {
 	F128,
 }
 
+// The levels of the descent below, loosest first, for printing an
+// expression back with only the parens it needs.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Precedence {
+	Pattern,
+	Coalesce,
+	Or,
+	And,
+	Equality,
+	Relation,
+	Additive,
+	Multiplicative,
+	Exponential,
+	Range,
+	Unary,
+	Replicate,
+	Select,
+	Primary,
+}
+
+// the level a binary operator is parsed at
+pub fn precedence(op: Name) -> Precedence {
+	match op {
+		Name::Pattern => Precedence::Pattern,
+		Name::Coalesce => Precedence::Coalesce,
+		Name::Or => Precedence::Or,
+		Name::And => Precedence::And,
+		Name::Eq | Name::Ne => Precedence::Equality,
+		Name::Gt | Name::Ge | Name::Lt | Name::Le => Precedence::Relation,
+		Name::Add | Name::Sub => Precedence::Additive,
+		Name::Mul | Name::Div => Precedence::Multiplicative,
+		Name::Exp => Precedence::Exponential,
+		Name::Range => Precedence::Range,
+		_ => Precedence::Select,
+	}
+}
+
+// capitalized labels name types, e.g. `Point` or `Vec'3`
+pub fn is_type_name(label: &str) -> bool {
+	label.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+}
+
+// the label a selection is by, a number selects the point at that
+// position, `xs.0`
+pub fn selector(ast: &AST) -> Option<&str> {
+	match ast {
+		AST::Ref(label) | AST::Number(Number::Integer, label) => Some(label),
+		_ => None,
+	}
+}
+
+// the position a numbered selector is, labels never start with a digit
+pub fn position(label: &str) -> Option<usize> {
+	label.parse().ok()
+}
+
+// an expression written only of types, as the typer finds them
+pub fn is_type(ast: &AST) -> bool {
+	match ast {
+		AST::Nothing | AST::Op2(Name::Or, _, _) => true,
+		AST::Ref(name) => {
+			is_type_name(name) || number::from_name(name).is_some()
+		}
+		AST::Array(items) => items.iter().all(is_type),
+		AST::Rep(original, _) => is_type(original),
+		AST::Graph(points) => {
+			!points.is_empty()
+				&& points.iter().all(|p| match p {
+					AST::Point(_, _, _, value) => is_type(value),
+					_ => false,
+				})
+		}
+		_ => number::range(ast).is_some(),
+	}
+}
+
+// `Circle{r: f64} | Rect{w: f64, h: f64} | Empty` as variant names and
+// their payload graphs, for points declaring a union
+pub fn variants(ast: &AST) -> Option<Vec<(&str, Option<&AST>)>> {
+	fn variant<'a>(ast: &'a AST, out: &mut Vec<(&'a str, Option<&'a AST>)>) -> bool {
+		match ast {
+			AST::Op2(Name::Or, left, right) => {
+				variant(left, out) && variant(right, out)
+			}
+			AST::Ref(name) if is_type_name(name) => {
+				out.push((name, None));
+				true
+			}
+			AST::Rep(original, payload) => match (&**original, &**payload) {
+				(AST::Ref(name), AST::Graph(_)) if is_type_name(name) => {
+					out.push((name, Some(payload)));
+					true
+				}
+				_ => false,
+			},
+			_ => false,
+		}
+	}
+	let mut out = vec![];
+	match ast {
+		AST::Op2(Name::Or, _, _) if variant(ast, &mut out) => Some(out),
+		_ => None,
+	}
+}
+
+// `[a, ..rest] ~ xs` or `{x: y: b} ~ point` as the pattern and the
+// value it takes apart
+pub fn destructuring(ast: &AST) -> Option<(&AST, &AST)> {
+	match ast {
+		AST::Op2(Name::Pattern, left, right) => match **left {
+			AST::Array(_) | AST::Graph(_) => Some((left, right)),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+// a duration is a whole number of milliseconds, `2s` is `2000`, None
+// when there are too many
+fn milliseconds(text: &str) -> Option<String> {
+	let digits: String = text.chars().filter(char::is_ascii_digit).collect();
+	let unit = match text.trim_start_matches(|c: char| c.is_ascii_digit() || c == '\'') {
+		"s" => 1_000,
+		"m" => 60_000,
+		"h" => 3_600_000,
+		_ => 1,
+	};
+	let count: u128 = digits.parse().ok()?;
+	Some(count.checked_mul(unit)?.to_string())
+}
+
 fn name_to_number(name: Name) -> Number {
 	match name {
 		Name::Decimal => Number::Decimal,
@@ -102,9 +234,22 @@ impl Tokens<'_> {
 
 		self.clear_stops();
 		let mut index = 0;
+		let mut chained: Vec<usize> = vec![];
 		while self.until(0, stops) {
+			// `a: b: expr`, a label directly followed by another label
+			// shares the expression that ends the chain
+			let chains = self.is(0, Name::Key) && self.is(1, Name::Key);
 			let (ast, ispoint) = self.point(index)?;
 			index += if ispoint { 1 } else { 0 };
+			if chains {
+				chained.push(points.len());
+			} else if let AST::Point(_, _, _, value) = &ast {
+				for link in chained.drain(..) {
+					if let AST::Point(_, _, _, shared) = &mut points[link] {
+						*shared = value.clone();
+					}
+				}
+			}
 			points.push(ast);
 			self.clear_stops();
 		}
@@ -134,7 +279,11 @@ impl Tokens<'_> {
 			String::new()
 		};
 
-		let expression = Box::new(if self.is(0, Name::Key) {
+		let empty = self.is(0, Name::Key)
+			|| self.of(0, Kind::Stop)
+			|| self.any(0, &[Name::Arrow, Name::BracketRT])
+			|| self.get(0).is_none();
+		let mut expression = Box::new(if empty {
 			AST::Nothing
 		} else {
 			let x = self.expression()?;
@@ -142,6 +291,26 @@ impl Tokens<'_> {
 			x
 		});
 
+		// `Name {a: i32, b: i32}` declares the graph type Name, given
+		// values, `Name {a: 1, b: 2}`, it constructs one
+		let mut label = label;
+		if label.is_empty() && !isreturn {
+			if let AST::Rep(original, argument) = &*expression {
+				if let (AST::Ref(name), AST::Graph(points)) =
+					(&**original, &**argument)
+				{
+					let labeled = points.iter().all(|p| match p {
+						AST::Point(label, _, _, _) => !label.is_empty(),
+						_ => true,
+					});
+					if labeled && is_type_name(name) && is_type(argument) {
+						label = name.clone();
+						expression = argument.clone();
+					}
+				}
+			}
+		}
+
 		Ok((AST::Point(label, index, isreturn, expression), true))
 	}
 
@@ -151,24 +320,31 @@ impl Tokens<'_> {
 	}
 
 	fn pattern_exp(&self) -> Result<AST, String> {
-		let mut left = self.or_exp()?;
+		let mut left = self.coalesce_exp()?;
 
-		// if left is type replicant, then check for pattern operator
-		match left {
-			AST::Array(_)
-			| AST::Tuple(_)
-			| AST::Graph(_)
-			| AST::Rep(_, _) => {
-				if self.is(0, Name::Pattern) {
-					self.eat(Name::Pattern)?;
-					left = AST::Op2(
-						Name::Pattern,
-						Box::new(left),
-						Box::new(self.or_exp()?),
-					);
-				}
-			}
-			_ => {}
+		// `Type ~ expression` annotates, `pattern ~ expression` binds
+		if self.is(0, Name::Pattern) {
+			self.eat(Name::Pattern)?;
+			left = AST::Op2(
+				Name::Pattern,
+				Box::new(left),
+				Box::new(self.coalesce_exp()?),
+			);
+		}
+
+		Ok(left)
+	}
+
+	// `reading ?? 0` stands in for a value that may be Nothing
+	fn coalesce_exp(&self) -> Result<AST, String> {
+		let mut left = self.or_exp()?;
+		while self.is(0, Name::Coalesce) {
+			self.eat(Name::Coalesce)?;
+			left = AST::Op2(
+				Name::Coalesce,
+				Box::new(left),
+				Box::new(self.or_exp()?),
+			);
 		}
 
 		Ok(left)
@@ -317,8 +493,8 @@ impl Tokens<'_> {
 
 	fn replicate_or_select(&self) -> Result<AST, String> {
 		let mut ret = self.select_exp()?;
-		if self.any(0, &[Name::ParenLF, Name::SquarenLF, Name::BracketLF]) {
-			ret = AST::Rep(Box::new(ret), Box::new(self.expression()?));
+		while self.any(0, &[Name::ParenLF, Name::SquarenLF, Name::BracketLF]) {
+			ret = AST::Rep(Box::new(ret), Box::new(self.primary_exp()?));
 		}
 		Ok(ret)
 		// }
@@ -326,8 +502,8 @@ impl Tokens<'_> {
 
 	fn select_exp(&self) -> Result<AST, String> {
 		let mut left = self.primary_exp()?;
-		while self.is(0, Name::Select) {
-			let t = self.eat(Name::Select)?;
+		while self.any(0, &[Name::Select, Name::Optional]) {
+			let t = self.eats(&[Name::Select, Name::Optional])?;
 			left = AST::Op2(
 				t.of.name,
 				Box::new(left),
@@ -416,6 +592,15 @@ impl Tokens<'_> {
 
 	fn number(&self) -> Result<AST, String> {
 		let t = self.eat_of(Kind::Number)?;
+		if t.of.name == Name::Duration {
+			return match milliseconds(&t.meta.text) {
+				Some(ms) => Ok(AST::Number(Number::Integer, ms)),
+				None => Err(format!(
+					"InvalidDuration: {:?} on line {}",
+					t.meta.text, t.meta.line
+				)),
+			};
+		}
 		Ok(AST::Number(name_to_number(t.of.name), t.meta.text.clone()))
 	}
 
@@ -538,3 +723,18 @@ impl Tokens<'_> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compiler::tokenizer;
+
+	#[test]
+	fn durations_too_long_are_reported() {
+		let ast = parser(&tokenizer::tokenizer("a: 2s")).unwrap();
+		assert!(format!("{:?}", ast).contains("\"2000\""));
+		let long = "a: 999999999999999999999999999999999999h";
+		let err = parser(&tokenizer::tokenizer(long)).unwrap_err();
+		assert!(err.starts_with("InvalidDuration"), "{}", err);
+	}
+}
@@ -0,0 +1,1000 @@
+use super::delta::{self, Applied, Change};
+use super::parser::{is_type_name, AST};
+use super::reducer::{cast, compare, conforms, within, Reducer, Value};
+use super::reduction::{self, Reduction};
+use super::tokenizer::Name;
+use super::typer::Type;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+// The reactor keeps a program alive. It evaluates the program once,
+// then when input points change computes again only the points reading
+// them, directly or not, in dependency order, so no point is computed
+// from some inputs changed and others not yet. A point computed to the
+// value it had stops the change there. A computation failing leaves
+// every point as it was before the change. Each update is a tick, prev
+// reads the values points had when it began.
+//
+// An input array may be changed item by item. The points computing
+// from it with `map`, `filter` and `sort` then change their arrays item
+// by item too, and `sum` of integers and a fold given an inverse their
+// value, so a change costs about as much as the items it touches.
+//
+// Inputs changing together, a width and a height, are set in a
+// transaction. Its values are checked as they are set but given to the
+// points only on commit, with one pass over their dependents, and a
+// transaction rolled back or dropped changes nothing.
+//
+// A value a stream emits is a tick too, the Scheduler gives it with
+// `emit` and the points reading it through `latest` follow.
+
+pub struct Reactor<'a> {
+	pub reducer: Reducer<'a>,
+	rank: Vec<usize>,     // per point, position in the dependency order
+	stateful: Vec<usize>, // points reading others through prev
+	kept: HashMap<usize, Vec<bool>>, // filter -> items kept, by item
+	open: Vec<bool>, // per point, whether it reads a parameter
+}
+
+// the points one tick changed, and what they held before to leave them
+// as they were when it fails
+#[derive(Default)]
+struct Pass {
+	changed: HashSet<usize>,
+	seeded: HashSet<usize>, // points reading a changed point through prev
+	stale: HashSet<usize>,  // points not computed reading a changed point
+	deltas: HashMap<usize, Vec<Applied>>, // arrays changed item by item
+	values: HashMap<usize, Option<Value>>, // point -> value before
+	edits: Vec<(usize, Vec<Applied>)>,     // arrays changed in place
+	previous: HashMap<usize, Option<Value>>, // point -> value prev read
+	latest: HashMap<usize, Option<Value>>,   // stream point -> value before
+}
+
+impl Pass {
+	// whether the points reading a point must be computed again
+	fn moved(&self, id: usize) -> bool {
+		self.changed.contains(&id) || self.stale.contains(&id)
+	}
+}
+
+// a point holding another's value, or computing its value with a
+// built-in reducer from the argument points given, in parameter order
+enum Derivation {
+	Copy(usize),
+	Reduce(Reduction, Vec<Option<usize>>),
+}
+
+impl<'a> Reactor<'a> {
+	pub fn new(ast: &'a AST) -> Result<Self, String> {
+		let mut reducer = Reducer::new(ast)?;
+		let mut rank = vec![0; reducer.types.len()];
+		for (i, id) in reducer.deps.order.iter().enumerate() {
+			rank[*id] = i;
+		}
+		// a graph holding parameters is a function, not open itself
+		let mut open = vec![false; reducer.types.len()];
+		for id in &reducer.deps.order {
+			let point = &reducer.deps.table.points[*id];
+			let reads = &reducer.deps.edges[*id];
+			open[*id] = match point.value {
+				AST::Nothing => !point.label.is_empty(),
+				AST::Graph(_) => false,
+				_ => reads.iter().any(|read| open[*read]),
+			};
+		}
+		let delayed = &reducer.deps.delayed;
+		let stateful = (0..delayed.len()).filter(|id| !delayed[*id].is_empty()).collect();
+		reducer.point(0)?;
+		let mut reactor = Reactor {
+			reducer,
+			rank,
+			stateful,
+			kept: HashMap::new(),
+			open,
+		};
+		// a filter follows changes from the first one on
+		for id in reactor.reducer.deps.order.clone() {
+			reactor.keep(id)?;
+		}
+		Ok(reactor)
+	}
+
+	// the point at a path, `size.width`
+	pub fn find(&self, path: &str) -> Option<usize> {
+		let points = &self.reducer.deps.table.points;
+		points.iter().position(|p| p.path == path)
+	}
+
+	pub fn value(&mut self, id: usize) -> Result<Value, String> {
+		self.reducer.point(id)
+	}
+
+	// points reading no other point, literals and parameters, can be
+	// given values from outside
+	pub fn input(&self, id: usize) -> bool {
+		let table = &self.reducer.deps.table;
+		let point = &table.points[id];
+		self.reducer.deps.edges[id].is_empty()
+			&& self.reducer.deps.delayed[id].is_empty()
+			&& !is_type_name(&point.label)
+			&& !matches!(point.value, AST::Graph(_))
+			&& point.binding.is_none()
+			&& table.destructures(id).is_none()
+	}
+
+	// gives an input point a value, returning the points whose values
+	// changed in dependency order
+	pub fn set(&mut self, id: usize, value: Value) -> Result<Vec<usize>, String> {
+		self.update(vec![(id, value)])
+	}
+
+	pub fn begin(&mut self) -> Transaction<'_, 'a> {
+		Transaction {
+			reactor: self,
+			inputs: vec![],
+		}
+	}
+
+	// gives input points their values together and computes the points
+	// reading them once
+	pub fn update(
+		&mut self,
+		inputs: Vec<(usize, Value)>,
+	) -> Result<Vec<usize>, String> {
+		let mut accepted = vec![];
+		for (id, value) in inputs {
+			accepted.push((id, self.accept(id, value)?));
+		}
+		self.pass(|reactor, pass| {
+			for (id, value) in accepted {
+				let old = reactor.reducer.values[id].replace(value);
+				if old != reactor.reducer.values[id] {
+					pass.changed.insert(id);
+				}
+				pass.values.entry(id).or_insert(old);
+			}
+			Ok(())
+		})
+	}
+
+	// changes items of an input array, the points computing from it with
+	// `map`, `filter`, `sort`, `sum` and `fold` given an inverse follow
+	// the changes, others are computed again
+	pub fn edit(
+		&mut self,
+		id: usize,
+		changes: Vec<Change>,
+	) -> Result<Vec<usize>, String> {
+		let changes = self.accept_changes(id, changes)?;
+		self.reducer.point(id)?;
+		self.pass(|reactor, pass| {
+			let path = &reactor.reducer.deps.table.points[id].path;
+			let items = match &mut reactor.reducer.values[id] {
+				Some(Value::Array(items)) => items,
+				_ => return Err(format!("NotAnArray: {}", path)),
+			};
+			let applied = delta::apply(items, changes)
+				.map_err(|msg| format!("{} in {}", msg, path))?;
+			if !applied.is_empty() {
+				pass.changed.insert(id);
+				pass.edits.push((id, applied.clone()));
+				pass.deltas.insert(id, applied);
+			}
+			Ok(())
+		})
+	}
+
+	// a value the stream of a point emitted, the points reading it with
+	// `latest` are computed again
+	pub fn emit(&mut self, id: usize, value: Value) -> Result<Vec<usize>, String> {
+		let point = &self.reducer.deps.table.points[id];
+		if !matches!(self.reducer.types[id], Type::Stream(_)) {
+			return Err(format!("NotAStream: {}", point.path));
+		}
+		self.pass(|reactor, pass| {
+			let old = reactor.reducer.latest.insert(id, value);
+			pass.latest.entry(id).or_insert(old);
+			pass.changed.insert(id);
+			Ok(())
+		})
+	}
+
+	// one tick, `inputs` changes input points and the points reading
+	// them are computed again, or nothing changes if one fails
+	fn pass<F>(&mut self, inputs: F) -> Result<Vec<usize>, String>
+	where
+		F: FnOnce(&mut Self, &mut Pass) -> Result<(), String>,
+	{
+		let mut pass = Pass::default();
+		match self.run(&mut pass, inputs) {
+			Ok(()) => {
+				let mut changed: Vec<usize> = pass.changed.into_iter().collect();
+				changed.sort_by_key(|id| self.rank[*id]);
+				Ok(changed)
+			}
+			Err(msg) => {
+				self.restore(pass);
+				Err(msg)
+			}
+		}
+	}
+
+	fn run<F>(&mut self, pass: &mut Pass, inputs: F) -> Result<(), String>
+	where
+		F: FnOnce(&mut Self, &mut Pass) -> Result<(), String>,
+	{
+		// the tick ending is the one prev reads from now on
+		pass.seeded = self.tick(pass)?;
+		inputs(self, pass)?;
+		let mut dependents: Vec<usize> = self
+			.reducer
+			.dependents(pass.changed.iter().chain(&pass.seeded).copied())
+			.into_iter()
+			.chain(pass.seeded.iter().copied())
+			.collect::<HashSet<usize>>()
+			.into_iter()
+			.collect();
+		dependents.sort_by_key(|id| self.rank[*id]);
+		for id in dependents {
+			// not computed yet, so computed from the new values on use,
+			// the points reading it are computed again
+			if self.reducer.values[id].is_none() {
+				pass.values.entry(id).or_insert(None);
+				pass.stale.insert(id);
+				continue;
+			}
+			let reads = &self.reducer.deps.edges[id];
+			if !reads.iter().any(|read| pass.moved(*read))
+				&& !pass.seeded.contains(&id)
+			{
+				continue;
+			}
+			// a graph holds the values of its points, it is put together
+			// again only when read
+			if matches!(self.reducer.deps.table.points[id].value, AST::Graph(_)) {
+				let old = self.reducer.values[id].take();
+				pass.values.entry(id).or_insert(old);
+				pass.changed.insert(id);
+				continue;
+			}
+			if self.follow(id, pass)? {
+				continue;
+			}
+			let old = self.reducer.values[id].take();
+			pass.values.entry(id).or_insert(old.clone());
+			self.kept.remove(&id);
+			let v = self.reducer.point(id)?;
+			// a point reading a parameter holds Nothing, what it computes
+			// once given values may still have changed
+			if old.as_ref() != Some(&v) || self.open[id] {
+				pass.changed.insert(id);
+			}
+			// a filter computed again follows the next changes
+			self.keep(id)?;
+		}
+		Ok(())
+	}
+
+	// keeps the values of the points read through prev, returning the
+	// points reading one whose value is not the one kept before; every
+	// prev moves on, not only those the inputs changing reach
+	fn tick(&mut self, pass: &mut Pass) -> Result<HashSet<usize>, String> {
+		let mut moved = HashSet::new();
+		for id in self.stateful.clone() {
+			for target in self.reducer.deps.delayed[id].clone() {
+				let v = self.reducer.point(target)?;
+				if self.reducer.previous.get(&target) != Some(&v) {
+					let old = self.reducer.previous.insert(target, v);
+					pass.previous.entry(target).or_insert(old);
+					moved.insert(target);
+				}
+			}
+		}
+		let delayed = &self.reducer.deps.delayed;
+		Ok(self
+			.stateful
+			.iter()
+			.filter(|id| delayed[**id].iter().any(|t| moved.contains(t)))
+			.copied()
+			.collect())
+	}
+
+	// how a point computes its value from other points, when it may
+	// follow changes to an array
+	fn derivation(&self, id: usize) -> Option<Derivation> {
+		let table = &self.reducer.deps.table;
+		let point = &table.points[id];
+		match point.value {
+			AST::Ref(_) | AST::Op2(Name::Select, _, _) => {
+				table.target(point.scope, point.value).map(Derivation::Copy)
+			}
+			AST::Rep(original, argument) => {
+				let r = match &**original {
+					AST::Ref(name) if table.resolve(point.scope, name).is_none() => {
+						reduction::from_name(name)?
+					}
+					_ => return None,
+				};
+				let args = match &**argument {
+					AST::Graph(_) => {
+						let scope = table.graph(argument)?;
+						let args = table.scopes[scope]
+							.points
+							.iter()
+							.map(|p| (table.points[*p].label.clone(), *p))
+							.collect();
+						reduction::optional_arguments(
+							reduction::name(r),
+							reduction::parameters(r),
+							reduction::required(r),
+							args,
+							&point.path,
+						)
+						.ok()?
+					}
+					_ => vec![Some(table.target(point.scope, argument)?)],
+				};
+				Some(Derivation::Reduce(r, args))
+			}
+			_ => None,
+		}
+	}
+
+	// follows the changes to the one array a point read that changed,
+	// false when the point must be computed again instead
+	fn follow(&mut self, id: usize, pass: &mut Pass) -> Result<bool, String> {
+		if pass.seeded.contains(&id) {
+			return Ok(false);
+		}
+		let reads = &self.reducer.deps.edges[id];
+		let mut changed = reads.iter().filter(|read| pass.moved(**read));
+		let from = match (changed.next(), changed.next()) {
+			(Some(from), None) => *from,
+			_ => return Ok(false),
+		};
+		let delta = match pass.deltas.get(&from) {
+			Some(delta) => delta.clone(),
+			None => return Ok(false),
+		};
+		let (r, args) = match self.derivation(id) {
+			Some(Derivation::Copy(target)) if target == from => {
+				return self.follow_array(id, pass, |_, out, applied| {
+					delta::redo(out, &delta);
+					applied.extend(delta.iter().cloned());
+					Ok(())
+				})
+			}
+			Some(Derivation::Reduce(r, args)) if args[0] == Some(from) => (r, args),
+			_ => return Ok(false),
+		};
+		let arg = |i: usize| args.get(i).copied().flatten();
+		match r {
+			Reduction::Map => {
+				let f = self.argument(arg(1))?;
+				self.follow_array(id, pass, |reactor, out, applied| {
+					reactor.map(id, &f, &delta, out, applied)
+				})
+			}
+			Reduction::Filter => {
+				let f = self.argument(arg(1))?;
+				let mut kept = match self.kept.remove(&id) {
+					Some(kept) => kept,
+					None => return Ok(false),
+				};
+				let followed = self.follow_array(id, pass, |reactor, out, applied| {
+					reactor.filter(id, &f, &delta, &mut kept, out, applied)
+				})?;
+				self.kept.insert(id, kept);
+				Ok(followed)
+			}
+			Reduction::Sort => self.follow_array(id, pass, |reactor, out, applied| {
+				reactor.sort(id, &delta, out, applied)
+			}),
+			Reduction::Sum => self.fold(id, pass, None, None, &delta),
+			Reduction::Fold => {
+				let f = self.argument(arg(2))?;
+				let inverse = match arg(3) {
+					Some(inverse) => Some(self.reducer.point(inverse)?),
+					None => None,
+				};
+				self.fold(id, pass, Some(f), inverse, &delta)
+			}
+			_ => Ok(false),
+		}
+	}
+
+	fn argument(&mut self, arg: Option<usize>) -> Result<Value, String> {
+		match arg {
+			Some(id) => self.reducer.point(id),
+			None => Ok(Value::Nothing),
+		}
+	}
+
+	// changes the array a point holds in place, taking the changes back
+	// if one fails
+	fn follow_array<F>(
+		&mut self,
+		id: usize,
+		pass: &mut Pass,
+		change: F,
+	) -> Result<bool, String>
+	where
+		F: FnOnce(&mut Self, &mut Vec<Value>, &mut Vec<Applied>) -> Result<(), String>,
+	{
+		let mut out = match self.reducer.values[id].take() {
+			Some(Value::Array(items)) => items,
+			other => {
+				self.reducer.values[id] = other;
+				return Ok(false);
+			}
+		};
+		let mut applied = vec![];
+		let result = change(self, &mut out, &mut applied);
+		if result.is_err() {
+			delta::undo(&mut out, &applied);
+		}
+		self.reducer.values[id] = Some(Value::Array(out));
+		result?;
+		// kept even when empty, the items a filter keeps may have changed
+		pass.edits.push((id, applied.clone()));
+		if !applied.is_empty() {
+			pass.changed.insert(id);
+			pass.deltas.insert(id, applied);
+		}
+		Ok(true)
+	}
+
+	fn map(
+		&mut self,
+		id: usize,
+		f: &Value,
+		delta: &[Applied],
+		out: &mut Vec<Value>,
+		applied: &mut Vec<Applied>,
+	) -> Result<(), String> {
+		for change in delta {
+			applied.push(match change {
+				Applied::Insert(i, v) => {
+					let y = self.reducer.apply_for(id, f, vec![v.clone()])?;
+					out.insert(*i, y.clone());
+					Applied::Insert(*i, y)
+				}
+				Applied::Remove(i, _) => Applied::Remove(*i, out.remove(*i)),
+				Applied::Update(i, _, v) => {
+					let y = self.reducer.apply_for(id, f, vec![v.clone()])?;
+					let old = std::mem::replace(&mut out[*i], y.clone());
+					Applied::Update(*i, old, y)
+				}
+			});
+		}
+		Ok(())
+	}
+
+	// `kept` has for each item of the array filtered whether it is kept,
+	// the items kept before one are where it is in the filtered array
+	fn filter(
+		&mut self,
+		id: usize,
+		f: &Value,
+		delta: &[Applied],
+		kept: &mut Vec<bool>,
+		out: &mut Vec<Value>,
+		applied: &mut Vec<Applied>,
+	) -> Result<(), String> {
+		for change in delta {
+			let i = match change {
+				Applied::Insert(i, _) | Applied::Remove(i, _) | Applied::Update(i, _, _) => *i,
+			};
+			let at = kept[..i].iter().filter(|k| **k).count();
+			let was = match change {
+				Applied::Insert(_, _) => false,
+				_ => kept[i],
+			};
+			let keep = match change {
+				Applied::Insert(_, v) | Applied::Update(_, _, v) => {
+					self.reducer.apply_for(id, f, vec![v.clone()])? == Value::Boolean(true)
+				}
+				Applied::Remove(_, _) => false,
+			};
+			match change {
+				Applied::Insert(_, _) => kept.insert(i, keep),
+				Applied::Remove(_, _) => {
+					kept.remove(i);
+				}
+				Applied::Update(_, _, _) => kept[i] = keep,
+			}
+			let v = match change {
+				Applied::Insert(_, v) | Applied::Update(_, _, v) => v,
+				Applied::Remove(_, v) => v,
+			};
+			match (was, keep) {
+				(true, true) => {
+					let old = std::mem::replace(&mut out[at], v.clone());
+					applied.push(Applied::Update(at, old, v.clone()));
+				}
+				(true, false) => applied.push(Applied::Remove(at, out.remove(at))),
+				(false, true) => {
+					out.insert(at, v.clone());
+					applied.push(Applied::Insert(at, v.clone()));
+				}
+				(false, false) => {}
+			}
+		}
+		Ok(())
+	}
+
+	// items taken out are found and new items placed by binary search,
+	// after the items equal to them
+	fn sort(
+		&mut self,
+		id: usize,
+		delta: &[Applied],
+		out: &mut Vec<Value>,
+		applied: &mut Vec<Applied>,
+	) -> Result<(), String> {
+		for change in delta {
+			let (old, new) = match change {
+				Applied::Insert(_, v) => (None, Some(v)),
+				Applied::Remove(_, v) => (Some(v), None),
+				Applied::Update(_, old, v) => (Some(old), Some(v)),
+			};
+			if let Some(old) = old {
+				let i = self.search(id, out, old, false)?;
+				applied.push(Applied::Remove(i, out.remove(i)));
+			}
+			if let Some(v) = new {
+				let i = self.search(id, out, v, true)?;
+				out.insert(i, v.clone());
+				applied.push(Applied::Insert(i, v.clone()));
+			}
+		}
+		Ok(())
+	}
+
+	// the first item of a sorted array not before `v`, or after it
+	fn search(
+		&self,
+		id: usize,
+		items: &[Value],
+		v: &Value,
+		after: bool,
+	) -> Result<usize, String> {
+		let (mut lo, mut hi) = (0, items.len());
+		while lo < hi {
+			let mid = (lo + hi) / 2;
+			let order = compare(&items[mid], v).ok_or(format!(
+				"InvalidOperands: {} < {} in {}",
+				items[mid],
+				v,
+				self.reducer.deps.table.points[id].path
+			))?;
+			if order == Ordering::Less || (after && order == Ordering::Equal) {
+				lo = mid + 1;
+			} else {
+				hi = mid;
+			}
+		}
+		Ok(lo)
+	}
+
+	// `sum` adds and subtracts the items changed, a fold given an
+	// inverse takes out the items before with it, and a fold without
+	// follows only items added at the end; a sum of floats is computed
+	// again, subtracting would drift from adding the items left
+	fn fold(
+		&mut self,
+		id: usize,
+		pass: &mut Pass,
+		f: Option<Value>,
+		inverse: Option<Value>,
+		delta: &[Applied],
+	) -> Result<bool, String> {
+		let sum = f.is_none();
+		if !sum && inverse.is_none() {
+			let from = match self.derivation(id) {
+				Some(Derivation::Reduce(_, args)) => args[0],
+				_ => None,
+			};
+			let len = match from.and_then(|from| self.reducer.values[from].as_ref()) {
+				Some(Value::Array(items)) => items.len(),
+				_ => return Ok(false),
+			};
+			let inserted = delta
+				.iter()
+				.filter(|c| matches!(c, Applied::Insert(_, _)))
+				.count();
+			// items inserted and removed again leave fewer than were
+			// inserted, which is no append
+			let mut len = match len.checked_sub(inserted) {
+				Some(len) => len,
+				None => return Ok(false),
+			};
+			for change in delta {
+				match change {
+					Applied::Insert(i, _) if *i == len => len += 1,
+					_ => return Ok(false),
+				}
+			}
+		}
+		let old = self.reducer.values[id].clone();
+		if sum && matches!(old, Some(Value::Decimal(_))) {
+			return Ok(false);
+		}
+		let mut acc = old.clone().unwrap_or(Value::Nothing);
+		for change in delta {
+			if let Applied::Remove(_, v) | Applied::Update(_, v, _) = change {
+				acc = match &inverse {
+					Some(inverse) => self.reducer.apply_for(id, inverse, vec![acc, v.clone()])?,
+					None => self.reducer.binary(Name::Sub, &acc, v)?,
+				};
+			}
+			if let Applied::Insert(_, v) | Applied::Update(_, _, v) = change {
+				acc = match &f {
+					Some(f) => self.reducer.apply_for(id, f, vec![acc, v.clone()])?,
+					None => self.reducer.binary(Name::Add, &acc, v)?,
+				};
+			}
+		}
+		pass.values.entry(id).or_insert(old.clone());
+		if old.as_ref() != Some(&acc) {
+			pass.changed.insert(id);
+		}
+		self.reducer.values[id] = Some(acc);
+		Ok(true)
+	}
+
+	// whether each item of the array a filter reads is kept, for the
+	// filter to follow changes to the array
+	fn keep(&mut self, id: usize) -> Result<(), String> {
+		let args = match self.derivation(id) {
+			Some(Derivation::Reduce(Reduction::Filter, args)) => args,
+			_ => return Ok(()),
+		};
+		let items = match self.argument(args[0])? {
+			Value::Array(items) => items,
+			_ => return Ok(()),
+		};
+		let f = self.argument(args.get(1).copied().flatten())?;
+		let mut kept = vec![];
+		for v in items {
+			kept.push(self.reducer.apply_for(id, &f, vec![v])? == Value::Boolean(true));
+		}
+		self.kept.insert(id, kept);
+		Ok(())
+	}
+
+	// a value for an input point, of its type and within its bounds
+	fn accept(&self, id: usize, value: Value) -> Result<Value, String> {
+		let point = &self.reducer.deps.table.points[id];
+		if !self.input(id) {
+			return Err(format!("NotAnInput: {}", point.path));
+		}
+		// a parameter has no value until one arrives
+		if matches!(point.value, AST::Nothing) && value == Value::Nothing {
+			return Ok(value);
+		}
+		let t = match &self.reducer.types[id] {
+			Type::Type(t) => t,
+			t => t,
+		};
+		if !conforms(t, &value) {
+			return Err(format!(
+				"TypeMismatch: expected {} found {} in {}",
+				t, value, point.path
+			));
+		}
+		self.reducer.check(id, &value)?;
+		Ok(cast(t, value))
+	}
+
+	// changes to an input array, items of its type and within bounds
+	fn accept_changes(
+		&self,
+		id: usize,
+		changes: Vec<Change>,
+	) -> Result<Vec<Change>, String> {
+		let point = &self.reducer.deps.table.points[id];
+		if !self.input(id) {
+			return Err(format!("NotAnInput: {}", point.path));
+		}
+		let item = match &self.reducer.types[id] {
+			Type::Array(item) => item,
+			_ => return Err(format!("NotAnArray: {}", point.path)),
+		};
+		let mut accepted = vec![];
+		for change in changes {
+			let (i, v) = match &change {
+				Change::Insert(i, v) | Change::Update(i, v) => (*i, v.clone()),
+				Change::Remove(_) => {
+					accepted.push(change);
+					continue;
+				}
+			};
+			if !conforms(item, &v) {
+				return Err(format!(
+					"TypeMismatch: expected {} found {} in {}",
+					item, v, point.path
+				));
+			}
+			within(item, &v).map_err(|msg| format!("{} in {}", msg, point.path))?;
+			let v = cast(item, v);
+			accepted.push(match change {
+				Change::Insert(_, _) => Change::Insert(i, v),
+				_ => Change::Update(i, v),
+			});
+		}
+		Ok(accepted)
+	}
+
+	fn restore(&mut self, pass: Pass) {
+		for (id, applied) in pass.edits.iter().rev() {
+			if let Some(Value::Array(items)) = &mut self.reducer.values[*id] {
+				delta::undo(items, applied);
+			}
+			self.kept.remove(id);
+		}
+		for (id, v) in pass.values {
+			self.reducer.values[id] = v;
+			self.kept.remove(&id);
+		}
+		for (id, v) in pass.previous {
+			match v {
+				Some(v) => self.reducer.previous.insert(id, v),
+				None => self.reducer.previous.remove(&id),
+			};
+		}
+		for (id, v) in pass.latest {
+			match v {
+				Some(v) => self.reducer.latest.insert(id, v),
+				None => self.reducer.latest.remove(&id),
+			};
+		}
+	}
+}
+
+pub struct Transaction<'r, 'a> {
+	reactor: &'r mut Reactor<'a>,
+	inputs: Vec<(usize, Value)>, // point -> value, in the order set
+}
+
+impl Transaction<'_, '_> {
+	// the value an input point will have once committed, the last one
+	// set for a point
+	pub fn set(&mut self, id: usize, value: Value) -> Result<(), String> {
+		let value = self.reactor.accept(id, value)?;
+		match self.inputs.iter_mut().find(|(input, _)| *input == id) {
+			Some((_, v)) => *v = value,
+			None => self.inputs.push((id, value)),
+		}
+		Ok(())
+	}
+
+	// the value set for an input point so far
+	pub fn get(&self, id: usize) -> Option<&Value> {
+		let mut inputs = self.inputs.iter();
+		inputs.find(|(input, _)| *input == id).map(|(_, v)| v)
+	}
+
+	// gives the points their values, returning the points whose values
+	// changed in dependency order
+	pub fn commit(self) -> Result<Vec<usize>, String> {
+		self.reactor.update(self.inputs)
+	}
+
+	pub fn rollback(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compiler::{parser, tokenizer};
+
+	fn parse(source: &str) -> AST {
+		parser::parser(&tokenizer::tokenizer(source)).unwrap()
+	}
+
+	fn id(reactor: &Reactor, path: &str) -> usize {
+		reactor.find(path).unwrap()
+	}
+
+	// the paths of the points changed, graphs are put together again
+	// when read and left out
+	fn paths(reactor: &Reactor, ids: &[usize]) -> Vec<String> {
+		let points = &reactor.reducer.deps.table.points;
+		ids.iter()
+			.filter(|id| !matches!(points[**id].value, AST::Graph(_)))
+			.map(|id| points[*id].path.clone())
+			.collect()
+	}
+
+	fn value(reactor: &mut Reactor, path: &str) -> String {
+		let id = id(reactor, path);
+		reactor.value(id).unwrap().to_string()
+	}
+
+	#[test]
+	fn set_computes_only_the_dependents() {
+		let ast = parse("a: 1\nb: 2\nc: a + 1\nd: c * 2\ne: b + 1");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		assert_eq!(value(&mut reactor, "d"), "4");
+		let a = id(&reactor, "a");
+		let changed = reactor.set(a, Value::Integer(5)).unwrap();
+		assert_eq!(paths(&reactor, &changed), ["a", "c", "d"]);
+		assert_eq!(value(&mut reactor, "d"), "12");
+		assert_eq!(value(&mut reactor, "e"), "3");
+	}
+
+	#[test]
+	fn a_value_computed_again_unchanged_stops_the_change() {
+		let ast = parse("a: 1\nb: a > 0\nc: b & true");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		assert_eq!(value(&mut reactor, "c"), "true");
+		let a = id(&reactor, "a");
+		let changed = reactor.set(a, Value::Integer(2)).unwrap();
+		assert_eq!(paths(&reactor, &changed), ["a"]);
+	}
+
+	#[test]
+	fn no_point_sees_some_inputs_changed_and_others_not() {
+		let ast = parse("a: 1\nb: a + 1\nc: a + b");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		assert_eq!(value(&mut reactor, "c"), "3");
+		let a = id(&reactor, "a");
+		let changed = reactor.set(a, Value::Integer(10)).unwrap();
+		assert_eq!(paths(&reactor, &changed), ["a", "b", "c"]);
+		assert_eq!(value(&mut reactor, "c"), "21");
+	}
+
+	#[test]
+	fn a_failing_change_leaves_every_point_as_it_was() {
+		let ast = parse("a: 1\nb: 10 / a");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		assert_eq!(value(&mut reactor, "b"), "10");
+		let a = id(&reactor, "a");
+		let err = reactor.set(a, Value::Integer(0)).unwrap_err();
+		assert!(err.starts_with("DivisionByZero"), "{}", err);
+		assert_eq!(value(&mut reactor, "a"), "1");
+		assert_eq!(value(&mut reactor, "b"), "10");
+		let b = id(&reactor, "b");
+		assert!(reactor.set(b, Value::Integer(3)).is_err());
+	}
+
+	#[test]
+	fn a_transaction_gives_its_values_together_on_commit() {
+		let ast = parse("w: 2\nh: 3\narea: w * h");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		assert_eq!(value(&mut reactor, "area"), "6");
+		let (w, h) = (id(&reactor, "w"), id(&reactor, "h"));
+		let mut t = reactor.begin();
+		t.set(w, Value::Integer(4)).unwrap();
+		t.set(h, Value::Integer(5)).unwrap();
+		t.set(h, Value::Integer(6)).unwrap();
+		assert_eq!(t.get(h), Some(&Value::Integer(6)));
+		let changed = t.commit().unwrap();
+		assert_eq!(paths(&reactor, &changed), ["w", "h", "area"]);
+		assert_eq!(value(&mut reactor, "area"), "24");
+	}
+
+	#[test]
+	fn a_transaction_rolled_back_or_dropped_changes_nothing() {
+		let ast = parse("w: 2\nh: 3\narea: w * h");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		let (w, h) = (id(&reactor, "w"), id(&reactor, "h"));
+		let mut t = reactor.begin();
+		t.set(w, Value::Integer(4)).unwrap();
+		t.rollback();
+		let mut t = reactor.begin();
+		t.set(h, Value::Integer(4)).unwrap();
+		drop(t);
+		assert_eq!(value(&mut reactor, "area"), "6");
+	}
+
+	#[test]
+	fn a_transaction_checks_values_as_they_are_set() {
+		let ast = parse("w: 2\narea: w * 2");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		let (w, area) = (id(&reactor, "w"), id(&reactor, "area"));
+		let mut t = reactor.begin();
+		assert!(t.set(w, Value::String("wide".into())).is_err());
+		assert!(t.set(area, Value::Integer(1)).is_err());
+		assert_eq!(t.get(w), None);
+		assert_eq!(t.commit(), Ok(vec![]));
+	}
+
+	#[test]
+	fn prev_reads_the_value_of_the_tick_before() {
+		let source = "clicks: 0\ncount: prev{count, 0} + clicks\nlast: <clicks";
+		let ast = parse(source);
+		let mut reactor = Reactor::new(&ast).unwrap();
+		assert_eq!(value(&mut reactor, "count"), "0");
+		assert_eq!(value(&mut reactor, "last"), "Nothing");
+		let clicks = id(&reactor, "clicks");
+		reactor.set(clicks, Value::Integer(1)).unwrap();
+		assert_eq!(value(&mut reactor, "count"), "1");
+		assert_eq!(value(&mut reactor, "last"), "0");
+		reactor.set(clicks, Value::Integer(2)).unwrap();
+		assert_eq!(value(&mut reactor, "count"), "3");
+		assert_eq!(value(&mut reactor, "last"), "1");
+	}
+
+	#[test]
+	fn every_prev_moves_on_each_tick() {
+		let ast = parse("a: 0\nb: 0\nupdates: prev{updates, 0} + 1");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		assert_eq!(value(&mut reactor, "updates"), "1");
+		let (a, b) = (id(&reactor, "a"), id(&reactor, "b"));
+		reactor.set(a, Value::Integer(1)).unwrap();
+		reactor.set(b, Value::Integer(1)).unwrap();
+		assert_eq!(value(&mut reactor, "updates"), "3");
+	}
+
+	#[test]
+	fn followed_reductions_equal_computing_again() {
+		let source = "xs: [3, 1, 2]\n\
+			doubled: map{xs, {x: -> x * 2}}\n\
+			odd: filter{xs, {x: -> x - x / 2 * 2 = 1}}\n\
+			sorted: sort{xs}\n\
+			total: sum{xs}\n\
+			product: fold{xs, 1, {a: b: -> a * b}}\n\
+			folded: fold{xs, 0, {a: b: -> a + b}, {a: b: -> a - b}}";
+		let ast = parse(source);
+		let mut followed = Reactor::new(&ast).unwrap();
+		let mut computed = Reactor::new(&ast).unwrap();
+		let paths = ["doubled", "odd", "sorted", "total", "product", "folded"];
+		for path in paths {
+			value(&mut followed, path);
+		}
+		let xs = id(&followed, "xs");
+		let int = Value::Integer;
+		let edits = vec![
+			vec![Change::Insert(3, int(5))],
+			vec![Change::Update(0, int(4)), Change::Remove(1)],
+			vec![Change::Insert(0, int(7)), Change::Insert(0, int(9))],
+			vec![Change::Insert(1, int(6)), Change::Remove(1)],
+			vec![Change::Insert(5, int(8)), Change::Remove(5)],
+			vec![Change::Remove(0); 5],
+			vec![
+				Change::Insert(0, int(1)),
+				Change::Insert(0, int(2)),
+				Change::Remove(0),
+				Change::Remove(0),
+			],
+		];
+		for changes in edits {
+			followed.edit(xs, changes).unwrap();
+			let items = followed.value(xs).unwrap();
+			computed.set(xs, items).unwrap();
+			for path in paths {
+				let expected = value(&mut computed, path);
+				assert_eq!(value(&mut followed, path), expected, "{}", path);
+			}
+		}
+	}
+
+	#[test]
+	fn a_filter_follows_from_the_first_change() {
+		let ast = parse("xs: [3, 1, 2]\nbig: filter{xs, {x: -> x > 1}}");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		let big = id(&reactor, "big");
+		assert_eq!(reactor.kept.get(&big), Some(&vec![true, false, true]));
+		let xs = id(&reactor, "xs");
+		let change = vec![Change::Insert(0, Value::Integer(5))];
+		reactor.edit(xs, change).unwrap();
+		assert_eq!(value(&mut reactor, "big"), "[5, 3, 2]");
+		let kept = vec![true, true, false, true];
+		assert_eq!(reactor.kept.get(&big), Some(&kept));
+	}
+
+	#[test]
+	fn a_sum_of_floats_is_computed_again() {
+		let ast = parse("xs: [0.1, 0.2, 0.3]\ntotal: sum{xs}");
+		let mut reactor = Reactor::new(&ast).unwrap();
+		assert_eq!(value(&mut reactor, "total"), "0.6000000000000001");
+		let xs = id(&reactor, "xs");
+		reactor.edit(xs, vec![Change::Remove(0)]).unwrap();
+		assert_eq!(value(&mut reactor, "total"), "0.5");
+		let change = vec![Change::Update(1, Value::Decimal(0.7))];
+		reactor.edit(xs, change).unwrap();
+		assert_eq!(value(&mut reactor, "total"), "0.8999999999999999");
+	}
+}
@@ -6,3 +6,1762 @@ a: i32(1) i32(2) op(+)
 
 
 */
+use super::bytecode::{self, Code};
+use super::delay::{self, Delay};
+use super::dependency::{dependencies, Dependencies};
+use super::number;
+use super::parser::{
+	is_type_name, position, selector, variants, Number, AST,
+};
+use super::tokenizer::Name;
+use super::reduction::{self, Reduction};
+use super::scope::Step;
+use super::stream::{self, Stream};
+use super::typer::{self, symbol, Type};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+// Tree walking evaluation of a checked program. Every point is computed
+// once, on first use, from the points it reads, by its bytecode when it
+// compiled to some. Parameters have no value until one arrives, so they
+// and whatever is computed from them are Nothing. Replicating a graph
+// gives some of its points values and computes the points depending on
+// them again in a frame of their own.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	Nothing,
+	Boolean(bool),
+	Integer(i128),
+	Decimal(f64),
+	String(String),
+	Array(Vec<Value>),
+	Tuple(Vec<Value>),
+	Graph(Graph),
+	Variant(String, Vec<(String, Value)>),
+	Stream(Stream),
+	Type(Type),
+}
+
+// A graph value keeps the graph literal it came from and the points
+// given values on the way, so it can be replicated again.
+#[derive(Debug, Clone)]
+pub struct Graph {
+	pub scope: Option<usize>,
+	pub given: Vec<(usize, Value)>, // point -> value given
+	pub points: Vec<(String, Value)>, // unlabeled points have an empty label
+}
+
+// graphs are equal when their points are
+impl PartialEq for Graph {
+	fn eq(&self, other: &Graph) -> bool {
+		self.points == other.points
+	}
+}
+
+// points given values by a replication, and the points computed from
+// them again
+struct Frame {
+	given: HashMap<usize, Value>,
+	dependents: HashSet<usize>,
+	values: HashMap<usize, Value>,
+}
+
+pub fn reducer(ast: &AST) -> Result<Value, String> {
+	Reducer::new(ast)?.point(0)
+}
+
+pub struct Reducer<'a> {
+	pub deps: Dependencies<'a>,
+	pub types: Vec<Type>,           // per point, from the typer
+	pub values: Vec<Option<Value>>, // per point, once computed
+	readers: Vec<Vec<usize>>,       // per point, points reading it
+	frames: Vec<Frame>,             // replications being computed
+	pending: Vec<(usize, usize)>,   // frame depth and point computing
+	pub previous: HashMap<usize, Value>, // point -> value the tick before
+	pub latest: HashMap<usize, Value>,   // stream point -> value emitted last
+	pub code: Rc<Vec<Option<Code>>>,      // per point, if it compiled
+}
+
+impl<'a> Reducer<'a> {
+	pub fn new(ast: &'a AST) -> Result<Self, String> {
+		let deps = dependencies(ast)?;
+		let types = typer::types(ast)?;
+		let mut readers = vec![vec![]; types.len()];
+		for (id, reads) in deps.edges.iter().enumerate() {
+			for read in reads {
+				readers[*read].push(id);
+			}
+		}
+		let code = bytecode::compile(&deps.table, &types);
+		Ok(Reducer {
+			values: vec![None; types.len()],
+			readers,
+			frames: vec![],
+			pending: vec![],
+			previous: HashMap::new(),
+			latest: HashMap::new(),
+			code: Rc::new(code),
+			deps,
+			types,
+		})
+	}
+
+	pub fn point(&mut self, id: usize) -> Result<Value, String> {
+		for k in (0..self.frames.len()).rev() {
+			let frame = &self.frames[k];
+			if let Some(v) = frame.given.get(&id) {
+				return Ok(v.clone());
+			}
+			if frame.dependents.contains(&id) {
+				if let Some(v) = frame.values.get(&id) {
+					return Ok(v.clone());
+				}
+				// computed with the frames it could see when replicated
+				let above = self.frames.split_off(k + 1);
+				let v = self.compute(id);
+				self.frames.extend(above);
+				let v = v?;
+				self.frames[k].values.insert(id, v.clone());
+				return Ok(v);
+			}
+		}
+		if let Some(v) = &self.values[id] {
+			return Ok(v.clone());
+		}
+		let frames = std::mem::take(&mut self.frames);
+		let v = self.compute(id);
+		self.frames = frames;
+		let v = v?;
+		self.values[id] = Some(v.clone());
+		Ok(v)
+	}
+
+	fn compute(&mut self, id: usize) -> Result<Value, String> {
+		let key = (self.frames.len(), id);
+		if self.pending.contains(&key) {
+			return Err(format!(
+				"CyclicReference: {}",
+				self.deps.table.points[id].path
+			));
+		}
+		self.pending.push(key);
+		let point = &self.deps.table.points[id];
+		let (scope, value) = (point.scope, point.value);
+		let taken = point.binding.is_some()
+			|| self.deps.table.destructures(id).is_some();
+		let v = match &self.types[id] {
+			_ if taken => self.take(id),
+			_ if matches!(value, AST::Nothing) => Ok(Value::Nothing),
+			// a typed parameter, `b: Point`
+			Type::Type(_) if !is_type_name(&point.label) => Ok(Value::Nothing),
+			// a declared type other than a graph, `Percent: 0..100`
+			Type::Type(t) if !matches!(value, AST::Graph(_)) => {
+				Ok(Value::Type(*t.clone()))
+			}
+			_ => match &self.code.clone()[id] {
+				Some(code) => bytecode::run(self, code),
+				None => self.expression(scope, value),
+			},
+		};
+		self.pending.pop();
+		let v = v?;
+		self.check(id, &v)?;
+		Ok(v)
+	}
+
+	// a point taking a value apart holds that value, a name in its
+	// pattern the part its steps reach
+	fn take(&mut self, id: usize) -> Result<Value, String> {
+		let point = &self.deps.table.points[id];
+		if let Some((owner, steps)) = point.binding.clone() {
+			let mut v = self.point(owner)?;
+			for step in &steps {
+				v = self.part(v, step)?;
+			}
+			return Ok(v);
+		}
+		match self.deps.table.destructures(id) {
+			Some((pattern, matched)) => {
+				let v = self.expression(point.scope, matched)?;
+				self.destructure(pattern, &v)?;
+				Ok(v)
+			}
+			None => Ok(Value::Nothing),
+		}
+	}
+
+	// the value has the shape of the pattern, a missing value leaves
+	// every name Nothing
+	fn destructure(&self, pattern: &AST, v: &Value) -> Result<(), String> {
+		match (pattern, v) {
+			(_, Value::Nothing) => Ok(()),
+			(AST::Array(items) | AST::Tuple(items), v) => {
+				let rest = items
+					.iter()
+					.any(|item| matches!(item, AST::Op1(Name::Range, _)));
+				let fixed = items.len() - rest as usize;
+				if let Value::Array(values) | Value::Tuple(values) = v {
+					if fixed > values.len() || !rest && fixed < values.len() {
+						return Err(format!(
+							"PatternMismatch: {} items against {} in {}",
+							fixed,
+							v,
+							self.at()
+						));
+					}
+				}
+				for (i, item) in items.iter().enumerate() {
+					let (item, step) = match item {
+						AST::Op1(Name::Range, item) => (&**item, Step::Rest(i)),
+						_ => (item, Step::Index(i)),
+					};
+					self.destructure(item, &self.part(v.clone(), &step)?)?;
+				}
+				Ok(())
+			}
+			(AST::Graph(items), v) => {
+				for item in items {
+					if let AST::Point(label, _, _, value) = item {
+						if !label.is_empty() {
+							let step = Step::Label(label.clone());
+							self.destructure(value, &self.part(v.clone(), &step)?)?;
+						}
+					}
+				}
+				Ok(())
+			}
+			_ => Ok(()),
+		}
+	}
+
+	// one step into a value taken apart by a pattern
+	fn part(&self, v: Value, step: &Step) -> Result<Value, String> {
+		match (v, step) {
+			(Value::Nothing, _) => Ok(Value::Nothing),
+			(Value::Array(items) | Value::Tuple(items), Step::Index(i)) => {
+				match items.get(*i) {
+					Some(v) => Ok(v.clone()),
+					None => Err(format!(
+						"PatternMismatch: no item {} in {}",
+						i,
+						self.at()
+					)),
+				}
+			}
+			(Value::Array(items), Step::Rest(i)) => {
+				Ok(Value::Array(items.into_iter().skip(*i).collect()))
+			}
+			(Value::Tuple(items), Step::Rest(i)) => {
+				Ok(Value::Tuple(items.into_iter().skip(*i).collect()))
+			}
+			(v @ Value::Graph(_), Step::Label(label)) => {
+				self.select(&v, &AST::Ref(label.clone()))
+			}
+			(Value::Graph(graph), Step::Others(named)) => Ok(computed(
+				graph
+					.points
+					.into_iter()
+					.filter(|(l, _)| !named.contains(l))
+					.collect(),
+			)),
+			(v, Step::Index(_) | Step::Rest(_)) => Err(format!(
+				"PatternMismatch: expected an array found {} in {}",
+				v,
+				self.at()
+			)),
+			(v, _) => Err(format!(
+				"PatternMismatch: expected a graph found {} in {}",
+				v,
+				self.at()
+			)),
+		}
+	}
+
+	// a value arriving at a bounded point must be within its bounds
+	pub fn check(&self, id: usize, value: &Value) -> Result<(), String> {
+		let t = match &self.types[id] {
+			Type::Type(t) => t,
+			t => t,
+		};
+		within(t, value).map_err(|msg| {
+			format!("{} in {}", msg, self.deps.table.points[id].path)
+		})
+	}
+
+	fn expression(
+		&mut self,
+		scope: Option<usize>,
+		ast: &AST,
+	) -> Result<Value, String> {
+		if let Some(d) = delay::delay(&self.deps.table, scope, ast, self.at())? {
+			return self.delay(&d);
+		}
+		let table = &self.deps.table;
+		if let Some(l) = stream::latest(table, scope, ast, self.at())? {
+			return match self.latest.get(&l.target) {
+				Some(v) => Ok(v.clone()),
+				None => self.expression(l.scope, l.initial),
+			};
+		}
+		match ast {
+			AST::Nothing => Ok(Value::Nothing),
+			AST::Number(n, text) => number(*n, text)
+				.map_err(|msg| format!("{} in {}", msg, self.at())),
+			AST::String(text) => {
+				Ok(Value::String(text.trim_matches('"').to_string()))
+			}
+			AST::Array(list) => Ok(Value::Array(self.list(scope, list)?)),
+			AST::Tuple(list) => Ok(Value::Tuple(self.list(scope, list)?)),
+			AST::Graph(_) => self.graph(ast),
+			AST::Point(_, _, _, value) | AST::Arg(value) => {
+				self.expression(scope, value)
+			}
+			AST::Ref(name) => match self.deps.table.resolve(scope, name) {
+				Some(id) => self.point(id),
+				None => match typer::builtin(name) {
+					Some(t) => Ok(Value::Type(t)),
+					None => match self.deps.table.variant(scope, name) {
+						Some(id) => self.construct(scope, name, id, None),
+						None => Err(format!(
+							"UnresolvedReference: {} in {}",
+							name,
+							self.at()
+						)),
+					},
+				},
+			},
+			AST::Op2(Name::Select, left, right) => {
+				let value = self.expression(scope, left)?;
+				self.select(&value, right)
+			}
+			AST::Op2(Name::Optional, left, right) => {
+				match self.expression(scope, left)? {
+					Value::Nothing => Ok(Value::Nothing),
+					value => self.select(&value, right),
+				}
+			}
+			AST::Op2(Name::Coalesce, left, right) => {
+				match self.expression(scope, left)? {
+					Value::Nothing => self.expression(scope, right),
+					value => Ok(value),
+				}
+			}
+			AST::Op2(Name::Pattern, left, right) => {
+				self.pattern(scope, left, right)
+			}
+			AST::Op2(op, left, right) => {
+				let l = self.expression(scope, left)?;
+				let r = self.expression(scope, right)?;
+				match self.overload(scope, *op, vec![l.clone(), r.clone()])? {
+					Some(v) => Ok(v),
+					None => self.binary(*op, &l, &r),
+				}
+			}
+			AST::Op1(op, value) => {
+				let v = self.expression(scope, value)?;
+				match self.overload(scope, *op, vec![v.clone()])? {
+					Some(v) => Ok(v),
+					None => self.unary(*op, &v),
+				}
+			}
+			AST::Rep(original, argument) => {
+				if let AST::Ref(name) = &**original {
+					let table = &self.deps.table;
+					if table.resolve(scope, name).is_none() {
+						if let Some(n) = number::from_name(name) {
+							let v = self.expression(scope, argument)?;
+							return self.convert(n, &v);
+						}
+						if let Some(id) = table.variant(scope, name) {
+							return self.construct(
+								scope,
+								name,
+								id,
+								Some(argument),
+							);
+						}
+						if let Some(r) = reduction::from_name(name) {
+							return self.reduce(scope, r, argument);
+						}
+						if let Some(s) = stream::from_name(name) {
+							let args = self.builtin_arguments(
+								scope,
+								stream::parameters(s),
+								argument,
+							)?;
+							let args = reduction::arguments(
+								stream::name(s),
+								stream::parameters(s),
+								args,
+								self.at(),
+							)?;
+							return match stream::source(s, args) {
+								Ok(stream) => Ok(Value::Stream(stream)),
+								Err(msg) => Err(format!("{} in {}", msg, self.at())),
+							};
+						}
+						if let Some(c) = stream::combinator_from_name(name) {
+							let args = self.builtin_arguments(
+								scope,
+								stream::combinator_parameters(c),
+								argument,
+							)?;
+							let args = reduction::arguments(
+								stream::combinator_name(c),
+								stream::combinator_parameters(c),
+								args,
+								self.at(),
+							)?;
+							return match stream::combinator(c, args) {
+								Ok(Some(stream)) => Ok(Value::Stream(stream)),
+								Ok(None) => Ok(Value::Nothing),
+								Err(msg) => Err(format!("{} in {}", msg, self.at())),
+							};
+						}
+					}
+				}
+				self.replicate(scope, original, argument)
+			}
+		}
+	}
+
+	// the value a point had the tick before, the initial value before
+	// its first
+	fn delay(&mut self, d: &Delay) -> Result<Value, String> {
+		let target = delay::target(&self.deps.table, d, self.at())?;
+		match (self.previous.get(&target), d.initial) {
+			(Some(v), _) => Ok(v.clone()),
+			(None, Some(initial)) => self.expression(d.scope, initial),
+			(None, None) => Ok(Value::Nothing),
+		}
+	}
+
+	// `map{xs, {x: -> x * 2}}` and the other built-in reducers, a
+	// missing collection gives Nothing
+	fn reduce(
+		&mut self,
+		scope: Option<usize>,
+		r: Reduction,
+		argument: &AST,
+	) -> Result<Value, String> {
+		let args = self.builtin_arguments(
+			scope,
+			reduction::parameters(r),
+			argument,
+		)?;
+		let args = reduction::optional_arguments(
+			reduction::name(r),
+			reduction::parameters(r),
+			reduction::required(r),
+			args,
+			self.at(),
+		)?;
+		self.reduced(r, args.into_iter().flatten().collect())
+	}
+
+	// a built-in reducer given its arguments in parameter order
+	pub fn reduced(
+		&mut self,
+		r: Reduction,
+		args: Vec<Value>,
+	) -> Result<Value, String> {
+		// the function, or the other collection of `zip`, is the last
+		// argument required, the inverse of a fold is only of use to
+		// the reactor
+		let f = args[reduction::required(r) - 1].clone();
+		if let Value::Stream(from) = &args[0] {
+			return self.streamed(r, from, f);
+		}
+		let items = match self.items(&args[0])? {
+			Some(items) => items,
+			None => return Ok(Value::Nothing),
+		};
+		match r {
+			Reduction::Map => {
+				let mut mapped = vec![];
+				for (label, item) in items {
+					mapped.push((label, self.apply(&f, vec![item])?));
+				}
+				Ok(collect(&args[0], mapped))
+			}
+			Reduction::Filter => {
+				let mut kept = vec![];
+				for (label, item) in items {
+					if self.apply(&f, vec![item.clone()])? == Value::Boolean(true) {
+						kept.push((label, item));
+					}
+				}
+				Ok(match &args[0] {
+					Value::Graph(_) => computed(kept),
+					_ => Value::Array(kept.into_iter().map(|(_, v)| v).collect()),
+				})
+			}
+			Reduction::Fold | Reduction::Scan => {
+				let mut acc = args[1].clone();
+				let mut steps = vec![];
+				for (_, item) in items {
+					acc = self.apply(&f, vec![acc, item])?;
+					steps.push(acc.clone());
+				}
+				Ok(match r {
+					Reduction::Scan => Value::Array(steps),
+					_ => acc,
+				})
+			}
+			Reduction::Zip => {
+				let others = match self.items(&f)? {
+					Some(others) => others,
+					None => return Ok(Value::Nothing),
+				};
+				Ok(Value::Array(
+					items
+						.into_iter()
+						.zip(others)
+						.map(|((_, x), (_, y))| Value::Tuple(vec![x, y]))
+						.collect(),
+				))
+			}
+			Reduction::FlatMap => {
+				let mut flat = vec![];
+				for (_, item) in items {
+					let v = self.apply(&f, vec![item])?;
+					match self.items(&v)? {
+						Some(inner) => flat.extend(inner.into_iter().map(|(_, v)| v)),
+						None => flat.push(Value::Nothing),
+					}
+				}
+				Ok(Value::Array(flat))
+			}
+			Reduction::Sum => {
+				let mut total = Value::Integer(0);
+				for (_, item) in items {
+					total = self.binary(Name::Add, &total, &item)?;
+				}
+				Ok(total)
+			}
+			Reduction::Min | Reduction::Max => {
+				let op = match r {
+					Reduction::Min => Name::Lt,
+					_ => Name::Gt,
+				};
+				let mut best = Value::Nothing;
+				for (_, item) in items {
+					let better = match best {
+						Value::Nothing => true,
+						_ => self.binary(op, &item, &best)? == Value::Boolean(true),
+					};
+					if better {
+						best = item;
+					}
+				}
+				Ok(best)
+			}
+			Reduction::Any | Reduction::All => {
+				let all = r == Reduction::All;
+				for (_, item) in items {
+					let found = self.apply(&f, vec![item])? == Value::Boolean(true);
+					if found != all {
+						return Ok(Value::Boolean(found));
+					}
+				}
+				Ok(Value::Boolean(all))
+			}
+			Reduction::Sort => {
+				let mut sorted: Vec<Value> =
+					items.into_iter().map(|(_, v)| v).collect();
+				let mut failed = None;
+				sorted.sort_by(|l, r| {
+					compare(l, r).unwrap_or_else(|| {
+						failed.get_or_insert(self.operands(Name::Lt, l, r));
+						Ordering::Equal
+					})
+				});
+				match failed {
+					Some(msg) => Err(msg),
+					None => Ok(Value::Array(sorted)),
+				}
+			}
+		}
+	}
+
+	// `map`, `filter` and `zip` of a stream give a stream
+	fn streamed(
+		&self,
+		r: Reduction,
+		from: &Stream,
+		f: Value,
+	) -> Result<Value, String> {
+		let from = Box::new(from.clone());
+		Ok(Value::Stream(match (r, f) {
+			(Reduction::Zip, Value::Stream(other)) => {
+				Stream::Zip(from, Box::new(other))
+			}
+			(Reduction::Zip, Value::Nothing) => return Ok(Value::Nothing),
+			(Reduction::Zip, other) => {
+				return Err(format!("NotAStream: {} in {}", other, self.at()))
+			}
+			(Reduction::Map, f) => Stream::Map(from, Box::new(f)),
+			(Reduction::Filter, f) => Stream::Filter(from, Box::new(f)),
+			(r, _) => {
+				return Err(format!(
+					"NotACollection: {} given to {} in {}",
+					from,
+					reduction::name(r),
+					self.at()
+				))
+			}
+		}))
+	}
+
+	// the arguments of a built-in, the points of a graph or one value
+	fn builtin_arguments(
+		&mut self,
+		scope: Option<usize>,
+		params: &[&str],
+		argument: &AST,
+	) -> Result<Vec<(String, Value)>, String> {
+		if reduction::labeled(params, argument) {
+			return self.arguments(scope, argument);
+		}
+		Ok(vec![(String::new(), self.expression(scope, argument)?)])
+	}
+
+	// the items of a collection with their labels, unlabeled for
+	// arrays and tuples
+	fn items(&self, v: &Value) -> Result<Option<Vec<(String, Value)>>, String> {
+		match v {
+			Value::Nothing => Ok(None),
+			Value::Array(items) | Value::Tuple(items) => Ok(Some(
+				items.iter().map(|v| (String::new(), v.clone())).collect(),
+			)),
+			Value::Graph(graph) => Ok(Some(graph.points.clone())),
+			other => Err(format!(
+				"NotACollection: {} in {}",
+				other,
+				self.at()
+			)),
+		}
+	}
+
+	// a function applied on behalf of a point, by the streams running
+	// outside of its computation
+	pub fn apply_for(
+		&mut self,
+		id: usize,
+		f: &Value,
+		args: Vec<Value>,
+	) -> Result<Value, String> {
+		self.pending.push((self.frames.len(), id));
+		let v = self.apply(f, args);
+		self.pending.pop();
+		v
+	}
+
+	// an operator on graphs uses the operator key in scope whose
+	// parameters take the operands, as the typer resolved it
+	fn overload(
+		&mut self,
+		scope: Option<usize>,
+		op: Name,
+		operands: Vec<Value>,
+	) -> Result<Option<Value>, String> {
+		if !operands.iter().any(|v| matches!(v, Value::Graph(_))) {
+			return Ok(None);
+		}
+		let mut matches = vec![];
+		for id in self.deps.table.candidates(scope, symbol(op)) {
+			let pending = self.pending.iter().any(|(_, p)| *p == id);
+			if !pending && self.takes(id, &operands) {
+				matches.push(id);
+			}
+		}
+		match matches[..] {
+			[] => Ok(None),
+			[id] => {
+				let f = self.point(id)?;
+				self.apply(&f, operands).map(Some)
+			}
+			_ => Err(format!(
+				"AmbiguousOperator: {} in {}",
+				symbol(op),
+				self.at()
+			)),
+		}
+	}
+
+	// whether the function-like graph of a point takes the values, one
+	// per parameter, of the types its parameters declare
+	fn takes(&self, id: usize, args: &[Value]) -> bool {
+		let table = &self.deps.table;
+		let point = &table.points[id];
+		let graph = match table.graph_of(point.scope, point.value) {
+			Some(graph) => graph,
+			None => return false,
+		};
+		let points = &table.scopes[graph].points;
+		let (results, params): (Vec<usize>, Vec<usize>) =
+			points.iter().partition(|p| table.points[**p].isreturn);
+		!results.is_empty()
+			&& params.len() == args.len()
+			&& params.iter().zip(args).all(|(param, v)| {
+				let t = match &self.types[*param] {
+					Type::Type(t) => t,
+					t => t,
+				};
+				conforms(t, v)
+			})
+	}
+
+	// a function-like graph given values for its first parameters
+	fn apply(&mut self, f: &Value, args: Vec<Value>) -> Result<Value, String> {
+		match f {
+			Value::Graph(Graph {
+				scope: Some(graph),
+				given,
+				..
+			}) => {
+				let args = args.into_iter().map(|v| (String::new(), v)).collect();
+				self.instance(*graph, given.clone(), args)
+			}
+			Value::Nothing => Ok(Value::Nothing),
+			other => Err(format!("NotAFunction: {} in {}", other, self.at())),
+		}
+	}
+
+	// the original graph with the argument's points given, by label or
+	// by position, and every point reading them computed again
+	fn replicate(
+		&mut self,
+		scope: Option<usize>,
+		original: &AST,
+		argument: &AST,
+	) -> Result<Value, String> {
+		let f = self.expression(scope, original)?;
+		// the argument is computed for a graph only
+		if !matches!(f, Value::Graph(_)) {
+			return self.replicated(f, vec![]);
+		}
+		let args = self.arguments(scope, argument)?;
+		self.replicated(f, args)
+	}
+
+	// a graph value given the arguments, as replicating it does
+	pub fn replicated(
+		&mut self,
+		f: Value,
+		args: Vec<(String, Value)>,
+	) -> Result<Value, String> {
+		let graph = match f {
+			Value::Graph(graph) => graph,
+			// a function parameter not given yet
+			Value::Nothing => return Ok(Value::Nothing),
+			other => {
+				return Err(format!(
+					"InvalidReplication: {} is not a graph in {}",
+					other,
+					self.at()
+				))
+			}
+		};
+		match graph.scope {
+			Some(s) => self.instance(s, graph.given, args),
+			None => {
+				let mut points = graph.points;
+				let mut index = 0;
+				for (label, arg) in args {
+					let at = if label.is_empty() {
+						index += 1;
+						Some(index - 1).filter(|i| *i < points.len())
+					} else {
+						points.iter().position(|(l, _)| *l == label)
+					};
+					match at {
+						Some(i) => points[i].1 = arg,
+						None => return Err(self.argument(&label, None)),
+					}
+				}
+				Ok(Value::Graph(Graph {
+					scope: None,
+					given: vec![],
+					points,
+				}))
+			}
+		}
+	}
+
+	fn arguments(
+		&mut self,
+		scope: Option<usize>,
+		argument: &AST,
+	) -> Result<Vec<(String, Value)>, String> {
+		Ok(spread(self.expression(scope, argument)?))
+	}
+
+	fn instance(
+		&mut self,
+		graph: usize,
+		given: Vec<(usize, Value)>,
+		args: Vec<(String, Value)>,
+	) -> Result<Value, String> {
+		let points = self.deps.table.scopes[graph].points.clone();
+		let mut given: HashMap<usize, Value> = given.into_iter().collect();
+		let mut index = 0;
+		let mut ids = vec![];
+		for (label, arg) in args {
+			let id = if label.is_empty() {
+				// values pass over type parameters, `Pair(i32){1, 2}`,
+				// and nothing is given to a return point
+				let value = !matches!(arg, Value::Type(_));
+				while index < points.len()
+					&& (self.deps.table.points[points[index]].isreturn
+						|| value && self.type_parameter(points[index]))
+				{
+					index += 1;
+				}
+				index += 1;
+				points.get(index - 1).copied()
+			} else {
+				self.deps.table.scopes[graph].labels.get(&label).copied()
+			};
+			let id = match id {
+				Some(id) => id,
+				None => return Err(self.argument(&label, Some(graph))),
+			};
+			ids.push(id);
+			given.insert(id, arg);
+		}
+
+		let dependents = self.dependents(given.keys().copied());
+		self.frames.push(Frame {
+			given,
+			dependents,
+			values: HashMap::new(),
+		});
+		let values = self.given_points(&ids, &points);
+		let frame = self.frames.pop();
+		let values = values?;
+
+		// a function-like graph evaluates to its return points
+		let mut results: Vec<Value> = points
+			.iter()
+			.zip(&values)
+			.filter(|(id, _)| self.deps.table.points[**id].isreturn)
+			.map(|(_, (_, v))| v.clone())
+			.collect();
+		match results.len() {
+			0 => {}
+			1 => return Ok(results.remove(0)),
+			_ => return Ok(Value::Tuple(results)),
+		}
+		let given = frame.map(|f| f.given.into_iter().collect());
+		Ok(Value::Graph(Graph {
+			scope: Some(graph),
+			given: given.unwrap_or_default(),
+			points: values,
+		}))
+	}
+
+	// the points of an instance, values given to typed points take
+	// their type, which may depend on type parameters given with them
+	fn given_points(
+		&mut self,
+		ids: &[usize],
+		points: &[usize],
+	) -> Result<Vec<(String, Value)>, String> {
+		for id in ids {
+			let point = &self.deps.table.points[*id];
+			if is_type_name(&point.label) || matches!(point.value, AST::Nothing)
+			{
+				continue;
+			}
+			// a type given by name or union, `p: Pos | Nothing`, is the
+			// one the typer declared
+			let declared = match self.expression(point.scope, point.value) {
+				Ok(Value::Type(t)) => Some(t),
+				_ => match &self.types[*id] {
+					Type::Type(t) => Some(*t.clone()),
+					_ => None,
+				},
+			};
+			if let Some(t) = declared {
+				let frame = self.frames.len() - 1;
+				if let Some(v) = self.frames[frame].given.remove(id) {
+					let v = cast(&t, v);
+					within(&t, &v).map_err(|msg| {
+						format!("{} in {}", msg, self.deps.table.points[*id].path)
+					})?;
+					self.frames[frame].given.insert(*id, v);
+				}
+			}
+		}
+		let mut values = vec![];
+		for id in points {
+			let label = self.deps.table.points[*id].label.clone();
+			values.push((label, self.point(*id)?));
+		}
+		Ok(values)
+	}
+
+	fn argument(&self, label: &str, graph: Option<usize>) -> String {
+		let name = match graph {
+			Some(graph) => {
+				let owner = self.deps.table.scopes[graph].owner;
+				self.deps.table.points[owner].path.clone()
+			}
+			None => "graph".to_string(),
+		};
+		if label.is_empty() {
+			format!("TooManyArguments: given to {} in {}", name, self.at())
+		} else {
+			format!("UnknownLabel: {} on {} in {}", label, name, self.at())
+		}
+	}
+
+	// a capitalized parameter, `T:` in `Pair {T:, first: T}`
+	fn type_parameter(&self, id: usize) -> bool {
+		let point = &self.deps.table.points[id];
+		is_type_name(&point.label) && matches!(point.value, AST::Nothing)
+	}
+
+	// every point reading one of `ids`, directly or not
+	pub fn dependents<I>(&self, ids: I) -> HashSet<usize>
+	where
+		I: Iterator<Item = usize>,
+	{
+		let mut seen = HashSet::new();
+		let mut stack: Vec<usize> = ids.collect();
+		while let Some(id) = stack.pop() {
+			for reader in &self.readers[id] {
+				if seen.insert(*reader) {
+					stack.push(*reader);
+				}
+			}
+		}
+		seen
+	}
+
+	// values given to the frames being computed, innermost last
+	fn given(&self) -> Vec<(usize, Value)> {
+		let mut given: HashMap<usize, Value> = HashMap::new();
+		for frame in &self.frames {
+			for (id, v) in &frame.given {
+				given.insert(*id, v.clone());
+			}
+		}
+		given.into_iter().collect()
+	}
+
+	fn list(
+		&mut self,
+		scope: Option<usize>,
+		list: &[AST],
+	) -> Result<Vec<Value>, String> {
+		let mut values = vec![];
+		for item in list {
+			values.push(self.expression(scope, item)?);
+		}
+		Ok(values)
+	}
+
+	fn graph(&mut self, ast: &AST) -> Result<Value, String> {
+		let graph = match self.deps.table.graph(ast) {
+			Some(graph) => graph,
+			None => return Err("UnscopedGraph".to_string()),
+		};
+		let mut points = vec![];
+		for id in self.deps.table.scopes[graph].points.clone() {
+			let label = self.deps.table.points[id].label.clone();
+			points.push((label, self.point(id)?));
+		}
+		Ok(Value::Graph(Graph {
+			scope: Some(graph),
+			given: self.given(),
+			points,
+		}))
+	}
+
+	fn select(&self, value: &Value, right: &AST) -> Result<Value, String> {
+		match selector(right) {
+			Some(label) => self.selected(value, label),
+			None => Err(format!("InvalidSelector in {}", self.at())),
+		}
+	}
+
+	// the point labeled `label` of a graph or variant value, or at a
+	// position of a graph or tuple
+	pub fn selected(&self, value: &Value, label: &str) -> Result<Value, String> {
+		let at = position(label);
+		match value {
+			Value::Nothing => Ok(Value::Nothing),
+			Value::Tuple(items) if at.is_some_and(|i| i < items.len()) => {
+				Ok(items[at.unwrap_or(0)].clone())
+			}
+			Value::Graph(Graph { points, .. }) | Value::Variant(_, points) => {
+				let found = match at {
+					Some(i) => points.get(i),
+					None => points.iter().find(|(l, _)| l == label),
+				};
+				match found {
+					Some((_, v)) => Ok(v.clone()),
+					None => Err(format!(
+						"UnknownLabel: {} on {} in {}",
+						label,
+						value,
+						self.at()
+					)),
+				}
+			}
+			other => Err(format!(
+				"InvalidSelect: .{} on {} in {}",
+				label,
+				other,
+				self.at()
+			)),
+		}
+	}
+
+	// `Type ~ value` checks the value against the type, a union value
+	// before a graph of cases takes the case of its variant
+	fn pattern(
+		&mut self,
+		scope: Option<usize>,
+		left: &AST,
+		right: &AST,
+	) -> Result<Value, String> {
+		let declared = match number::range(left) {
+			Some((n, lo, hi)) => Value::Type(Type::Range(n, lo, hi)),
+			None => self.expression(scope, left)?,
+		};
+		match (declared, right) {
+			(Value::Variant(name, payload), AST::Graph(_)) => {
+				self.matches(&name, payload, right)
+			}
+			(Value::Type(t), _) => {
+				let v = self.expression(scope, right)?;
+				within(&t, &v).map_err(|msg| format!("{} in {}", msg, self.at()))?;
+				Ok(cast(&t, v))
+			}
+			_ => self.expression(scope, right),
+		}
+	}
+
+	fn matches(
+		&mut self,
+		variant: &str,
+		payload: Vec<(String, Value)>,
+		cases: &AST,
+	) -> Result<Value, String> {
+		let graph = match self.deps.table.graph(cases) {
+			Some(graph) => graph,
+			None => return Err("UnscopedGraph".to_string()),
+		};
+		let points = self.deps.table.scopes[graph].points.clone();
+		let case = points
+			.iter()
+			.find(|id| self.deps.table.points[**id].label == variant)
+			.or_else(|| {
+				points
+					.iter()
+					.find(|id| self.deps.table.points[**id].label == "_")
+			});
+		let id = match case {
+			Some(id) => *id,
+			None => {
+				return Err(format!(
+					"NonExhaustive: missing {} in {}",
+					variant,
+					self.at()
+				))
+			}
+		};
+		// a function-like case is called with the payload points its
+		// parameters name, `Circle: {r: -> r * r}`
+		match self.point(id)? {
+			Value::Graph(Graph {
+				scope: Some(graph),
+				given,
+				..
+			}) if self.returns(graph) => {
+				let labels = &self.deps.table.scopes[graph].labels;
+				let args = payload
+					.into_iter()
+					.filter(|(l, _)| labels.contains_key(l))
+					.collect();
+				self.instance(graph, given, args)
+			}
+			v => Ok(v),
+		}
+	}
+
+	fn returns(&self, graph: usize) -> bool {
+		self.deps.table.scopes[graph]
+			.points
+			.iter()
+			.any(|id| self.deps.table.points[*id].isreturn)
+	}
+
+	// a value of variant `name` of the union point `id`, its payload
+	// taken positionally or by label
+	fn construct(
+		&mut self,
+		scope: Option<usize>,
+		name: &str,
+		id: usize,
+		argument: Option<&AST>,
+	) -> Result<Value, String> {
+		let labels = self.payload(id, name);
+		let args = match argument {
+			Some(argument) => match self.expression(scope, argument)? {
+				Value::Graph(graph) => graph.points,
+				Value::Tuple(items) => {
+					items.into_iter().map(|v| (String::new(), v)).collect()
+				}
+				v => vec![(String::new(), v)],
+			},
+			None => vec![],
+		};
+		let mut fields: Vec<(String, Value)> =
+			labels.into_iter().map(|l| (l, Value::Nothing)).collect();
+		let mut index = 0;
+		for (label, arg) in args {
+			let at = if label.is_empty() {
+				index += 1;
+				Some(index - 1).filter(|i| *i < fields.len())
+			} else {
+				fields.iter().position(|(l, _)| *l == label)
+			};
+			match at {
+				Some(i) => fields[i].1 = cast(&self.field(id, name, i), arg),
+				None => {
+					return Err(format!(
+						"InvalidArgument: {} for {} in {}",
+						arg,
+						name,
+						self.at()
+					))
+				}
+			}
+		}
+		Ok(Value::Variant(name.to_string(), fields))
+	}
+
+	// the declared type of point `i` of the payload of variant `name`
+	fn field(&self, id: usize, name: &str, i: usize) -> Type {
+		let cases = match &self.types[id] {
+			Type::Type(t) => match &**t {
+				Type::Union(cases) => cases,
+				_ => return Type::Nothing,
+			},
+			_ => return Type::Nothing,
+		};
+		match cases.iter().find(|(n, _)| n == name) {
+			Some((_, Type::Graph(fields, _))) => match fields.get(i) {
+				Some((_, t)) => t.clone(),
+				None => Type::Nothing,
+			},
+			_ => Type::Nothing,
+		}
+	}
+
+	// labels of the payload of variant `name` of union point `id`
+	fn payload(&self, id: usize, name: &str) -> Vec<String> {
+		let table = &self.deps.table;
+		let payload = variants(table.points[id].value)
+			.unwrap_or_default()
+			.into_iter()
+			.find(|(n, _)| *n == name)
+			.and_then(|(_, payload)| payload)
+			.and_then(|payload| table.graph(payload));
+		match payload {
+			Some(graph) => table.scopes[graph]
+				.points
+				.iter()
+				.map(|id| match table.field(*id) {
+					Some(name) => name.to_string(),
+					None => table.points[*id].label.clone(),
+				})
+				.collect(),
+			None => vec![],
+		}
+	}
+
+	// `u16(x)` truncates and wraps like a cast
+	pub fn convert(&self, n: Number, v: &Value) -> Result<Value, String> {
+		let x = match v {
+			Value::Nothing => return Ok(Value::Nothing),
+			Value::Integer(x) => *x as f64,
+			Value::Decimal(x) => *x,
+			Value::Boolean(b) => *b as i128 as f64,
+			other => {
+				return Err(format!(
+					"InvalidConversion: {} to {} in {}",
+					other,
+					number::name(n),
+					self.at()
+				))
+			}
+		};
+		Ok(match (n, v) {
+			(Number::F32, _) => Value::Decimal(x as f32 as f64),
+			_ if number::is_float(n) => Value::Decimal(x),
+			(_, Value::Integer(i)) => Value::Integer(number::wrap(n, *i)),
+			_ => Value::Integer(number::wrap(n, x as i128)),
+		})
+	}
+
+	pub fn binary(&self, op: Name, l: &Value, r: &Value) -> Result<Value, String> {
+		let containers = container(l) || container(r);
+		match (op, l, r) {
+			(Name::Eq, _, _) => Ok(Value::Boolean(equal(l, r))),
+			(Name::Ne, _, _) => Ok(Value::Boolean(!equal(l, r))),
+			(_, Value::Nothing, _) | (_, _, Value::Nothing) => {
+				Ok(Value::Nothing)
+			}
+			(
+				Name::Add
+				| Name::Sub
+				| Name::Mul
+				| Name::Div
+				| Name::Exp
+				| Name::Gt
+				| Name::Ge
+				| Name::Lt
+				| Name::Le,
+				_,
+				_,
+			) if containers => self.elementwise(op, l, r),
+			(Name::Add, Value::String(a), Value::String(b)) => {
+				Ok(Value::String(format!("{}{}", a, b)))
+			}
+			(Name::Add | Name::Sub | Name::Mul | Name::Div | Name::Exp, _, _) => {
+				self.arithmetic(op, l, r)
+			}
+			(Name::Gt | Name::Ge | Name::Lt | Name::Le, _, _) => {
+				let ordering = match (l, r) {
+					(Value::String(a), Value::String(b)) => a.partial_cmp(b),
+					_ => decimal(l).partial_cmp(&decimal(r)),
+				};
+				let holds = match ordering {
+					Some(o) => match op {
+						Name::Gt => o.is_gt(),
+						Name::Ge => o.is_ge(),
+						Name::Lt => o.is_lt(),
+						_ => o.is_le(),
+					},
+					None => false,
+				};
+				Ok(Value::Boolean(holds))
+			}
+			(Name::And, Value::Boolean(a), Value::Boolean(b)) => {
+				Ok(Value::Boolean(*a && *b))
+			}
+			(Name::Or, Value::Boolean(a), Value::Boolean(b)) => {
+				Ok(Value::Boolean(*a || *b))
+			}
+			(Name::Range, Value::Integer(a), Value::Integer(b)) => {
+				self.range(*a, *b)
+			}
+			_ => Err(self.operands(op, l, r)),
+		}
+	}
+
+	fn arithmetic(&self, op: Name, l: &Value, r: &Value) -> Result<Value, String> {
+		match (l, r) {
+			(Value::Integer(a), Value::Integer(b)) => {
+				let (a, b) = (*a, *b);
+				let result = match op {
+					Name::Add => a.checked_add(b),
+					Name::Sub => a.checked_sub(b),
+					Name::Mul => a.checked_mul(b),
+					Name::Div if b == 0 => {
+						return Err(format!("DivisionByZero in {}", self.at()))
+					}
+					Name::Div => a.checked_div(b),
+					// a negative power is the integer division it comes
+					// to, `2 ^ -1` is `1 / 2`
+					_ if b < 0 => match a {
+						0 => {
+							return Err(format!("DivisionByZero in {}", self.at()))
+						}
+						1 => Some(1),
+						-1 => Some(if b % 2 == 0 { 1 } else { -1 }),
+						_ => Some(0),
+					},
+					_ => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+				};
+				match result {
+					Some(x) => Ok(Value::Integer(x)),
+					None => Err(format!(
+						"Overflow: {} {} {} in {}",
+						a,
+						symbol(op),
+						b,
+						self.at()
+					)),
+				}
+			}
+			(
+				Value::Integer(_) | Value::Decimal(_),
+				Value::Integer(_) | Value::Decimal(_),
+			) => {
+				let (a, b) = (decimal(l), decimal(r));
+				Ok(Value::Decimal(match op {
+					Name::Add => a + b,
+					Name::Sub => a - b,
+					Name::Mul => a * b,
+					Name::Div => a / b,
+					_ => a.powf(b),
+				}))
+			}
+			_ => Err(self.operands(op, l, r)),
+		}
+	}
+
+	// containers of the same shape pair up point by point, a scalar on
+	// either side goes with every element
+	fn elementwise(
+		&self,
+		op: Name,
+		l: &Value,
+		r: &Value,
+	) -> Result<Value, String> {
+		let each = |xs: &[Value], ys: &[Value]| -> Result<Vec<Value>, String> {
+			if xs.len() != ys.len() {
+				return Err(self.shape(op, l, r));
+			}
+			let mut items = vec![];
+			for (x, y) in xs.iter().zip(ys) {
+				items.push(self.binary(op, x, y)?);
+			}
+			Ok(items)
+		};
+		match (l, r) {
+			(Value::Array(xs), Value::Array(ys)) => Ok(Value::Array(each(xs, ys)?)),
+			(Value::Tuple(xs), Value::Tuple(ys)) => Ok(Value::Tuple(each(xs, ys)?)),
+			(Value::Graph(x), Value::Graph(y)) => {
+				let (xs, ys) = (&x.points, &y.points);
+				if xs.len() != ys.len() {
+					return Err(self.shape(op, l, r));
+				}
+				let mut points = vec![];
+				for (i, (label, x)) in xs.iter().enumerate() {
+					let y = if label.is_empty() {
+						ys.get(i).filter(|(l, _)| l.is_empty())
+					} else {
+						ys.iter().find(|(l, _)| l == label)
+					};
+					match y {
+						Some((_, y)) => {
+							points.push((label.clone(), self.binary(op, x, y)?))
+						}
+						None => return Err(self.shape(op, l, r)),
+					}
+				}
+				Ok(computed(points))
+			}
+			(container, scalar) if !self::container(scalar) => {
+				broadcast(container, &mut |item| self.binary(op, item, scalar))
+			}
+			(scalar, container) if !self::container(scalar) => {
+				broadcast(container, &mut |item| self.binary(op, scalar, item))
+			}
+			_ => Err(self.shape(op, l, r)),
+		}
+	}
+
+	pub fn unary(&self, op: Name, v: &Value) -> Result<Value, String> {
+		match (op, v) {
+			// `<x` reads prev before it gets here, `>x` and `:x` as a
+			// value have no meaning
+			(Name::Colon | Name::Gt | Name::Lt, _) => Err(format!(
+				"UnsupportedOperator: unary {} in {}",
+				symbol(op),
+				self.at()
+			)),
+			(_, Value::Nothing) => Ok(Value::Nothing),
+			(Name::Add | Name::Sub, _) if container(v) => {
+				broadcast(v, &mut |item| self.unary(op, item))
+			}
+			(Name::Add, Value::Integer(_) | Value::Decimal(_)) => Ok(v.clone()),
+			(Name::Sub, Value::Integer(x)) => Ok(Value::Integer(-x)),
+			(Name::Sub, Value::Decimal(x)) => Ok(Value::Decimal(-x)),
+			(Name::Not, Value::Boolean(b)) => Ok(Value::Boolean(!b)),
+			(Name::Length, _) => match v {
+				Value::Array(items) | Value::Tuple(items) => {
+					Ok(Value::Integer(items.len() as i128))
+				}
+				Value::Graph(graph) => {
+					Ok(Value::Integer(graph.points.len() as i128))
+				}
+				Value::String(text) => {
+					Ok(Value::Integer(text.chars().count() as i128))
+				}
+				_ => Err(self.operand(op, v)),
+			},
+			// `..n` counts up from zero
+			(Name::Range, Value::Integer(n)) => self.range(0, *n),
+			_ => Err(self.operand(op, v)),
+		}
+	}
+
+	// the integers from `a` to `b`, a range is held item by item so
+	// only so many
+	fn range(&self, a: i128, b: i128) -> Result<Value, String> {
+		if b.saturating_sub(a) >= RANGE_LIMIT {
+			return Err(format!(
+				"RangeTooLarge: {}..{} has more than {} items in {}",
+				a,
+				b,
+				RANGE_LIMIT,
+				self.at()
+			));
+		}
+		Ok(Value::Array((a..=b).map(Value::Integer).collect()))
+	}
+
+	fn operands(&self, op: Name, l: &Value, r: &Value) -> String {
+		format!(
+			"InvalidOperands: {} {} {} in {}",
+			l,
+			symbol(op),
+			r,
+			self.at()
+		)
+	}
+
+	fn operand(&self, op: Name, v: &Value) -> String {
+		format!("InvalidOperand: {}{} in {}", symbol(op), v, self.at())
+	}
+
+	fn shape(&self, op: Name, l: &Value, r: &Value) -> String {
+		format!("ShapeMismatch: {} {} {} in {}", l, symbol(op), r, self.at())
+	}
+
+	// path of the point being computed, for error messages
+	pub fn at(&self) -> &str {
+		match self.pending.last() {
+			Some((_, id)) => &self.deps.table.points[*id].path,
+			None => "",
+		}
+	}
+}
+
+// the most items a range is taken to
+const RANGE_LIMIT: i128 = 1 << 20;
+
+pub fn number(n: Number, text: &str) -> Result<Value, String> {
+	let digits = text.replace('\'', "");
+	let invalid = || format!("InvalidNumber: {}", text);
+	match n {
+		Number::Boolean => Ok(Value::Boolean(digits == "true")),
+		_ if number::is_float(n) => match digits.parse() {
+			Ok(x) => Ok(Value::Decimal(x)),
+			Err(_) => Err(invalid()),
+		},
+		_ => match digits.parse() {
+			Ok(x) => Ok(Value::Integer(x)),
+			Err(_) if !digits.is_empty() && digits.parse::<f64>().is_ok() => {
+				Err(format!(
+					"LiteralOutOfRange: {} outside {}",
+					text,
+					number::name(n)
+				))
+			}
+			Err(_) => Err(invalid()),
+		},
+	}
+}
+
+// the order of two numbers
+pub fn compare(l: &Value, r: &Value) -> Option<Ordering> {
+	match (l, r) {
+		(Value::Integer(x), Value::Integer(y)) => Some(x.cmp(y)),
+		(
+			Value::Integer(_) | Value::Decimal(_),
+			Value::Integer(_) | Value::Decimal(_),
+		) => decimal(l).partial_cmp(&decimal(r)),
+		_ => None,
+	}
+}
+
+fn decimal(v: &Value) -> f64 {
+	match v {
+		Value::Integer(x) => *x as f64,
+		Value::Decimal(x) => *x,
+		_ => f64::NAN,
+	}
+}
+
+// numbers compare by value whatever their representation
+fn equal(l: &Value, r: &Value) -> bool {
+	match (l, r) {
+		(Value::Integer(_), Value::Decimal(_))
+		| (Value::Decimal(_), Value::Integer(_)) => decimal(l) == decimal(r),
+		_ => l == r,
+	}
+}
+
+// a graph computed from others, with no literal to replicate
+// labeled points of a graph argument, or positional values
+pub fn spread(v: Value) -> Vec<(String, Value)> {
+	match v {
+		Value::Graph(graph) => graph.points,
+		Value::Tuple(items) | Value::Array(items) => {
+			items.into_iter().map(|v| (String::new(), v)).collect()
+		}
+		v => vec![(String::new(), v)],
+	}
+}
+
+fn computed(points: Vec<(String, Value)>) -> Value {
+	Value::Graph(Graph {
+		scope: None,
+		given: vec![],
+		points,
+	})
+}
+
+// mapped items in the shape of the collection they came from
+fn collect(like: &Value, items: Vec<(String, Value)>) -> Value {
+	match like {
+		Value::Graph(_) => computed(items),
+		Value::Tuple(_) => Value::Tuple(items.into_iter().map(|(_, v)| v).collect()),
+		_ => Value::Array(items.into_iter().map(|(_, v)| v).collect()),
+	}
+}
+
+fn container(v: &Value) -> bool {
+	matches!(v, Value::Array(_) | Value::Tuple(_) | Value::Graph(_))
+}
+
+fn broadcast<F>(v: &Value, f: &mut F) -> Result<Value, String>
+where
+	F: FnMut(&Value) -> Result<Value, String>,
+{
+	Ok(match v {
+		Value::Array(items) => {
+			Value::Array(items.iter().map(&mut *f).collect::<Result<_, _>>()?)
+		}
+		Value::Tuple(items) => {
+			Value::Tuple(items.iter().map(&mut *f).collect::<Result<_, _>>()?)
+		}
+		Value::Graph(graph) => {
+			let mut out = vec![];
+			for (label, item) in &graph.points {
+				out.push((label.clone(), f(item)?));
+			}
+			computed(out)
+		}
+		v => f(v)?,
+	})
+}
+
+// whether a value is one of a type
+pub fn conforms(t: &Type, v: &Value) -> bool {
+	match (t, v) {
+		(Type::Var(_), _) => true,
+		(Type::Maybe(_), Value::Nothing) => true,
+		(Type::Maybe(t), v) => conforms(t, v),
+		(Type::Number(_) | Type::Range(_, _, _), Value::Integer(_)) => true,
+		(Type::Number(n) | Type::Range(n, _, _), Value::Decimal(_)) => {
+			number::is_float(*n)
+		}
+		(Type::Array(t), Value::Array(items)) => {
+			items.iter().all(|v| conforms(t, v))
+		}
+		// a graph has the labeled points of its type, more may follow
+		(Type::Graph(fields, _), Value::Graph(graph)) => {
+			fields.iter().filter(|(l, _)| !l.is_empty()).all(|(l, t)| {
+				let mut points = graph.points.iter();
+				points.find(|(p, _)| p == l).is_some_and(|(_, v)| conforms(t, v))
+			})
+		}
+		(Type::Tuple(types), Value::Tuple(items)) => {
+			types.len() == items.len()
+				&& types.iter().zip(items).all(|(t, v)| conforms(t, v))
+		}
+		(Type::Nothing, Value::Nothing)
+		| (Type::Boolean, Value::Boolean(_))
+		| (Type::String, Value::String(_))
+		| (Type::Union(_), Value::Variant(_, _))
+		| (Type::Stream(_), Value::Stream(_)) => true,
+		_ => false,
+	}
+}
+
+// a number checked against bounds and the width of its type, Nothing
+// has not arrived yet
+pub fn within(t: &Type, v: &Value) -> Result<(), String> {
+	match (t, v) {
+		(Type::Number(n), Value::Integer(x)) if !number::holds(*n, *x) => {
+			Err(format!("Overflow: {} outside {}", v, t))
+		}
+		(Type::Range(_, lo, hi), Value::Integer(_) | Value::Decimal(_)) => {
+			let x = decimal(v);
+			if x < *lo || x > *hi {
+				Err(format!("RangeViolation: {} outside {}", v, t))
+			} else {
+				Ok(())
+			}
+		}
+		(Type::Maybe(t), _) => within(t, v),
+		_ => Ok(()),
+	}
+}
+
+// an integer held where a float is declared becomes one
+pub fn cast(t: &Type, v: Value) -> Value {
+	match (t, v) {
+		(Type::Number(n) | Type::Range(n, _, _), Value::Integer(x))
+			if number::is_float(*n) =>
+		{
+			Value::Decimal(x as f64)
+		}
+		(_, v) => v,
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Value::Nothing => write!(f, "Nothing"),
+			Value::Boolean(b) => write!(f, "{}", b),
+			Value::Integer(x) => write!(f, "{}", x),
+			Value::Decimal(x) => write!(f, "{:?}", x),
+			Value::String(text) => write!(f, "\"{}\"", text),
+			Value::Array(items) => write!(f, "[{}]", list(items)),
+			Value::Tuple(items) => write!(f, "({})", list(items)),
+			Value::Graph(graph) => write!(f, "{{{}}}", labeled(&graph.points)),
+			Value::Variant(name, points) if points.is_empty() => {
+				write!(f, "{}", name)
+			}
+			Value::Variant(name, points) => {
+				write!(f, "{}{{{}}}", name, labeled(points))
+			}
+			Value::Stream(stream) => write!(f, "{}", stream),
+			Value::Type(t) => write!(f, "{}", t),
+		}
+	}
+}
+
+fn list(items: &[Value]) -> String {
+	let items: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+	items.join(", ")
+}
+
+fn labeled(points: &[(String, Value)]) -> String {
+	let points: Vec<String> = points
+		.iter()
+		.map(|(label, v)| {
+			if label.is_empty() {
+				v.to_string()
+			} else {
+				format!("{}: {}", label, v)
+			}
+		})
+		.collect();
+	points.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compiler::{parser, tokenizer};
+
+	// the value of the point at `path`, or the error reducing gives
+	fn value_of(source: &str, path: &str) -> Result<String, String> {
+		let ast = parser::parser(&tokenizer::tokenizer(source))?;
+		let mut r = Reducer::new(&ast)?;
+		let points = &r.deps.table.points;
+		let id = points.iter().position(|p| p.path == path).unwrap();
+		Ok(r.point(id)?.to_string())
+	}
+
+	#[test]
+	fn integers_stay_within_their_width() {
+		let err = value_of("a: u8(200)\nb: a + u8(100)", "b").unwrap_err();
+		assert!(err.starts_with("Overflow"), "{}", err);
+		assert_eq!(value_of("a: u8(200)\nb: a + u8(55)", "b"), Ok("255".into()));
+		assert_eq!(value_of("a: u8(300)", "a"), Ok("44".into()));
+	}
+
+	#[test]
+	fn negative_powers_divide() {
+		assert_eq!(value_of("a: 2 ^ -1", "a"), Ok("0".into()));
+		assert_eq!(value_of("a: -1 ^ -3", "a"), Ok("-1".into()));
+		assert!(value_of("a: 0 ^ -1", "a").is_err());
+	}
+
+	#[test]
+	fn ranges_are_capped() {
+		let err = value_of("a: 0..100000000", "a").unwrap_err();
+		assert!(err.starts_with("RangeTooLarge"), "{}", err);
+		assert_eq!(value_of("a: 1..3", "a"), Ok("[1, 2, 3]".into()));
+	}
+
+	#[test]
+	fn literals_too_large_are_reported() {
+		let source = "a: 1000000000000000000000000000000000000000";
+		let err = value_of(source, "a").unwrap_err();
+		assert!(err.starts_with("LiteralOutOfRange"), "{}", err);
+	}
+
+	#[test]
+	fn graphs_are_arguments_only_by_parameter_name() {
+		assert_eq!(value_of("a: sum({a: 1, b: 2.5})", "a"), Ok("3.5".into()));
+		let named = "a: fold{items: [1, 2], initial: 0, \
+			function: {a: b: -> a + b}}";
+		assert_eq!(value_of(named, "a"), Ok("3".into()));
+		assert_eq!(value_of("a: sum{x: 1, y: 2}", "a"), Ok("3".into()));
+	}
+
+	#[test]
+	fn return_points_give_the_result() {
+		let single = "double: {x: -> x * 2}\nt: double(10)";
+		assert_eq!(value_of(single, "t"), Ok("20".into()));
+		let both = "f: {x: -> x + 1\n-> x * 2}\nk: f{3}";
+		assert_eq!(value_of(both, "k"), Ok("(4, 6)".into()));
+	}
+
+	#[test]
+	fn patterns_bind_the_parts_they_name() {
+		let rest = "pair: [1, 2, 3]\n\
+			[a, ..others] ~ pair\n\
+			pt: {x: 1, y: 2, z: 3}\n\
+			{x: _, ..fields} ~ pt";
+		assert_eq!(value_of(rest, "a"), Ok("1".into()));
+		assert_eq!(value_of(rest, "others"), Ok("[2, 3]".into()));
+		assert_eq!(value_of(rest, "fields"), Ok("{y: 2, z: 3}".into()));
+		let err = value_of("xs: [1, 2, 3]\n[a, b] ~ xs", "a").unwrap_err();
+		assert!(err.starts_with("PatternMismatch"), "{}", err);
+	}
+
+	#[test]
+	fn union_typed_parameters_take_values_and_nothing() {
+		let pos = "Pos: {x: f64, y: f64}\n\
+			f: {p: Pos | Nothing -> p?.x ?? 0.5}\n\
+			a: f{Pos{1, 2}}\n\
+			b: f{}";
+		assert_eq!(value_of(pos, "a"), Ok("1.0".into()));
+		assert_eq!(value_of(pos, "b"), Ok("0.5".into()));
+		assert_eq!(value_of("r: Nothing\nd: r ?? 3", "d"), Ok("3".into()));
+		assert_eq!(value_of("e: 4 ?? 3", "e"), Ok("4".into()));
+	}
+
+	#[test]
+	fn operators_on_graphs_use_the_operator_keys() {
+		let dot = "Vec: {x: f64, y: f64}\n\
+			*: { l: r: Vec -> l.x * r.x + l.y * r.y }\n\
+			s: Vec{x: 1.0, y: 2.0} * Vec{x: 3.0, y: 4.0}";
+		assert_eq!(value_of(dot, "s"), Ok("11.0".into()));
+		let point = "Point: {a: i32, b: i32}\n\
+			+: { l: r: Point -> Point{l.a - r.a, l.b * r.b} }\n\
+			o: Point{a: 1, b: 2} + Point{a: 3, b: 4}\n\
+			e: {a: 1, b: 2} * {a: 3, b: 4}";
+		assert_eq!(value_of(point, "o"), Ok("{a: -2, b: 8}".into()));
+		assert_eq!(value_of(point, "e"), Ok("{a: 3, b: 8}".into()));
+	}
+}
@@ -0,0 +1,199 @@
+// Built-in reducers, the higher order functions collections are
+// processed with in place of loops. Each takes its arguments by
+// position or by parameter name, `fold{xs, 0, add}` or
+// `fold{items: xs, initial: 0, function: add}`, and goes through the
+// items of an array, tuple, range or graph value. Parameters after the
+// required ones may be left out. A graph written as the argument
+// gives arguments by name when all its labels name parameters, any
+// other graph is one value, `sum{{a: 1, b: 2.5}}`.
+
+use super::parser::AST;
+use super::scope::Table;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reduction {
+	Map,
+	Filter,
+	Fold,
+	Scan,
+	Zip,
+	FlatMap,
+	Sum,
+	Min,
+	Max,
+	Any,
+	All,
+	Sort,
+}
+
+pub fn from_name(name: &str) -> Option<Reduction> {
+	match name {
+		"map" => Some(Reduction::Map),
+		"filter" => Some(Reduction::Filter),
+		"fold" => Some(Reduction::Fold),
+		"scan" => Some(Reduction::Scan),
+		"zip" => Some(Reduction::Zip),
+		"flat'map" => Some(Reduction::FlatMap),
+		"sum" => Some(Reduction::Sum),
+		"min" => Some(Reduction::Min),
+		"max" => Some(Reduction::Max),
+		"any" => Some(Reduction::Any),
+		"all" => Some(Reduction::All),
+		"sort" => Some(Reduction::Sort),
+		_ => None,
+	}
+}
+
+pub fn name(r: Reduction) -> &'static str {
+	match r {
+		Reduction::Map => "map",
+		Reduction::Filter => "filter",
+		Reduction::Fold => "fold",
+		Reduction::Scan => "scan",
+		Reduction::Zip => "zip",
+		Reduction::FlatMap => "flat'map",
+		Reduction::Sum => "sum",
+		Reduction::Min => "min",
+		Reduction::Max => "max",
+		Reduction::Any => "any",
+		Reduction::All => "all",
+		Reduction::Sort => "sort",
+	}
+}
+
+pub fn parameters(r: Reduction) -> &'static [&'static str] {
+	match r {
+		Reduction::Map
+		| Reduction::Filter
+		| Reduction::FlatMap
+		| Reduction::Any
+		| Reduction::All => &["items", "function"],
+		// a fold given the inverse of its function, `sub` of `add`, is
+		// taken not to depend on the order of the items
+		Reduction::Fold => &["items", "initial", "function", "inverse"],
+		Reduction::Scan => &["items", "initial", "function"],
+		Reduction::Zip => &["items", "others"],
+		Reduction::Sum | Reduction::Min | Reduction::Max | Reduction::Sort => {
+			&["items"]
+		}
+	}
+}
+
+// how many of the parameters must be given
+pub fn required(r: Reduction) -> usize {
+	match r {
+		Reduction::Fold => 3,
+		r => parameters(r).len(),
+	}
+}
+
+// a graph argument gives its points as arguments when every labeled
+// one names a parameter, `fold{items: xs, initial: 0, function: add}`
+pub fn labeled(params: &[&str], argument: &AST) -> bool {
+	match argument {
+		AST::Graph(points) => points.iter().all(|point| match point {
+			AST::Point(label, _, _, _) => {
+				label.is_empty() || params.contains(&&label[..])
+			}
+			_ => true,
+		}),
+		_ => false,
+	}
+}
+
+// the arguments of a built-in in parameter order, given by position
+// or by parameter name
+pub fn arguments<T>(
+	name: &str,
+	params: &[&str],
+	args: Vec<(String, T)>,
+	at: &str,
+) -> Result<Vec<T>, String> {
+	let found = optional_arguments(name, params, params.len(), args, at)?;
+	Ok(found.into_iter().flatten().collect())
+}
+
+// the scope and expressions of the arguments of a built-in given a
+// graph, `prev{count, 0}`, in parameter order; none may be left out
+pub fn expressions<'a>(
+	table: &Table,
+	scope: Option<usize>,
+	argument: &'a AST,
+	name: &str,
+	parameters: &[&str],
+	at: &str,
+) -> Result<(Option<usize>, Vec<&'a AST>), String> {
+	let (scope, args) = match argument {
+		AST::Graph(points) => (
+			table.graph(argument).or(scope),
+			points.iter().map(|p| match p {
+				AST::Point(label, _, _, value) => (label.clone(), &**value),
+				other => (String::new(), other),
+			}),
+		),
+		_ => {
+			return Err(format!(
+				"MissingArgument: {} for {} in {}",
+				parameters[parameters.len() - 1],
+				name,
+				at
+			))
+		}
+	};
+	let args = arguments(name, parameters, args.collect(), at)?;
+	Ok((scope, args))
+}
+
+// the arguments of a built-in whose parameters after the first
+// `required` may be left out, those are None
+pub fn optional_arguments<T>(
+	name: &str,
+	params: &[&str],
+	required: usize,
+	args: Vec<(String, T)>,
+	at: &str,
+) -> Result<Vec<Option<T>>, String> {
+	let count = args.len();
+	let mut slots: Vec<Option<T>> = params.iter().map(|_| None).collect();
+	let mut index = 0;
+	for (label, arg) in args {
+		let i = if label.is_empty() {
+			index += 1;
+			index - 1
+		} else {
+			match params.iter().position(|p| *p == label) {
+				Some(i) => i,
+				None => {
+					return Err(format!(
+						"UnknownLabel: {} on {} in {}",
+						label,
+						name,
+						at
+					))
+				}
+			}
+		};
+		match slots.get_mut(i) {
+			Some(slot) => *slot = Some(arg),
+			None => {
+				return Err(format!(
+					"TooManyArguments: {} given to {} in {}",
+					count,
+					name,
+					at
+				))
+			}
+		}
+	}
+	for (param, slot) in params.iter().zip(&slots).take(required) {
+		if slot.is_none() {
+			return Err(format!(
+				"MissingArgument: {} for {} in {}",
+				param,
+				name,
+				at
+			));
+		}
+	}
+	Ok(slots)
+}
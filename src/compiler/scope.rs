@@ -0,0 +1,468 @@
+use super::number;
+use super::parser::{
+	destructuring, is_type_name, position, selector, variants, AST,
+};
+use super::tokenizer::Name;
+use std::collections::HashMap;
+
+// Flat symbol table over a parsed program. Every Point in the AST gets
+// an id, every Graph gets a scope, and references can be resolved
+// statically by walking the scope chain.
+
+#[derive(Debug)]
+pub struct Point<'a> {
+	pub label: String,
+	pub path: String,
+	pub index: u16,
+	pub isreturn: bool,
+	pub scope: Option<usize>, // scope the point is declared in
+	pub value: &'a AST,
+	pub binding: Option<(usize, Vec<Step>)>, // pattern point, path in it
+}
+
+// one step from the value a pattern matches to a part it binds
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+	Index(usize),        // item of an array or tuple
+	Rest(usize),         // items from this one on, `..rest`
+	Label(String),       // point of a graph
+	Others(Vec<String>), // points not named by the pattern
+}
+
+#[derive(Debug)]
+pub struct Scope<'a> {
+	pub parent: Option<usize>,
+	pub owner: usize, // point whose expression holds the graph
+	pub points: Vec<usize>,
+	pub labels: HashMap<String, usize>,
+	pub variants: HashMap<String, usize>, // variant -> union point
+	pub connections: Vec<&'a AST>,        // `:name` entries
+}
+
+#[derive(Debug)]
+pub struct Table<'a> {
+	pub points: Vec<Point<'a>>,
+	pub scopes: Vec<Scope<'a>>,
+	graphs: HashMap<*const AST, usize>,
+	nodes: HashMap<*const AST, usize>,
+}
+
+pub fn scope(ast: &AST) -> Result<Table<'_>, String> {
+	let mut table = Table {
+		points: vec![],
+		scopes: vec![],
+		graphs: HashMap::new(),
+		nodes: HashMap::new(),
+	};
+	match ast {
+		AST::Point(label, index, isreturn, value) => {
+			table.nodes.insert(ast as *const AST, 0);
+			table.points.push(Point {
+				label: label.clone(),
+				path: label.clone(),
+				index: *index,
+				isreturn: *isreturn,
+				scope: None,
+				value,
+				binding: None,
+			});
+			table.walk(value, None, 0);
+			Ok(table)
+		}
+		_ => Err("ExpectedProgramPoint".to_string()),
+	}
+}
+
+impl<'a> Table<'a> {
+	fn walk(&mut self, ast: &'a AST, scope: Option<usize>, owner: usize) {
+		match ast {
+			AST::Graph(list) => {
+				let id = self.scopes.len();
+				self.graphs.insert(ast as *const AST, id);
+				self.scopes.push(Scope {
+					parent: scope,
+					owner,
+					points: vec![],
+					labels: HashMap::new(),
+					variants: HashMap::new(),
+					connections: vec![],
+				});
+				for item in list {
+					match item {
+						AST::Point(label, index, isreturn, value) => {
+							let point = self.points.len();
+							let name = if label.is_empty() {
+								index.to_string()
+							} else {
+								label.clone()
+							};
+							let path = if owner == 0 {
+								name
+							} else {
+								let parent = &self.points[owner].path;
+								format!("{}.{}", parent, name)
+							};
+							self.nodes.insert(item as *const AST, point);
+							self.points.push(Point {
+								label: label.clone(),
+								path,
+								index: *index,
+								isreturn: *isreturn,
+								scope: Some(id),
+								value,
+								binding: None,
+							});
+							self.scopes[id].points.push(point);
+							if !label.is_empty() {
+								let labels = &mut self.scopes[id].labels;
+								labels.insert(label.clone(), point);
+							}
+							// `[a, b] ~ pair` declares a and b next to it
+							if let Some((pattern, matched)) =
+								self.destructures(point)
+							{
+								self.walk(matched, Some(id), point);
+								self.bind(pattern, id, point, vec![]);
+								continue;
+							}
+							let cases = match variants(value) {
+								Some(cases) if is_type_name(label) => cases,
+								_ => vec![],
+							};
+							for (name, _) in cases {
+								let variants = &mut self.scopes[id].variants;
+								variants.insert(name.to_string(), point);
+							}
+							self.walk(value, Some(id), point);
+						}
+						AST::Op1(Name::Colon, target) => {
+							self.scopes[id].connections.push(target);
+							self.walk(target, Some(id), owner);
+						}
+						_ => self.walk(item, Some(id), owner),
+					}
+				}
+			}
+			AST::Array(list) | AST::Tuple(list) => {
+				for item in list {
+					self.walk(item, scope, owner);
+				}
+			}
+			AST::Point(_, _, _, value) => self.walk(value, scope, owner),
+			AST::Op2(_, left, right) | AST::Rep(left, right) => {
+				self.walk(left, scope, owner);
+				self.walk(right, scope, owner);
+			}
+			AST::Op1(_, value) | AST::Arg(value) => {
+				self.walk(value, scope, owner)
+			}
+			AST::Nothing
+			| AST::Number(_, _)
+			| AST::String(_)
+			| AST::Ref(_) => {}
+		}
+	}
+
+	// a point for every name in a pattern, reached from the matched
+	// value by `steps`
+	fn bind(
+		&mut self,
+		pattern: &'a AST,
+		scope: usize,
+		owner: usize,
+		steps: Vec<Step>,
+	) {
+		let step = |step: Step| {
+			let mut steps = steps.clone();
+			steps.push(step);
+			steps
+		};
+		match pattern {
+			AST::Array(items) | AST::Tuple(items) => {
+				for (i, item) in items.iter().enumerate() {
+					match item {
+						AST::Op1(Name::Range, rest) => {
+							self.bind(rest, scope, owner, step(Step::Rest(i)))
+						}
+						_ => self.bind(item, scope, owner, step(Step::Index(i))),
+					}
+				}
+			}
+			AST::Graph(items) => {
+				let named: Vec<String> = items
+					.iter()
+					.filter_map(|item| match item {
+						AST::Point(label, _, _, _) if !label.is_empty() => {
+							Some(label.clone())
+						}
+						_ => None,
+					})
+					.collect();
+				for item in items {
+					let (label, value) = match item {
+						AST::Point(label, _, _, value) => (label, &**value),
+						_ => continue,
+					};
+					match value {
+						// `{x:}` binds x, `{x: b}` binds b
+						AST::Nothing if !label.is_empty() => {
+							let steps = step(Step::Label(label.clone()));
+							self.bound(label, value, scope, owner, steps);
+						}
+						_ if !label.is_empty() => {
+							let steps = step(Step::Label(label.clone()));
+							self.bind(value, scope, owner, steps);
+						}
+						AST::Op1(Name::Range, rest) => {
+							let steps = step(Step::Others(named.clone()));
+							self.bind(rest, scope, owner, steps);
+						}
+						_ => {}
+					}
+				}
+			}
+			AST::Ref(name) if name != "_" => {
+				self.bound(name, pattern, scope, owner, steps)
+			}
+			_ => {}
+		}
+	}
+
+	fn bound(
+		&mut self,
+		name: &str,
+		value: &'a AST,
+		scope: usize,
+		owner: usize,
+		steps: Vec<Step>,
+	) {
+		let id = self.points.len();
+		let parent = self.scopes[scope].owner;
+		let path = if parent == 0 {
+			name.to_string()
+		} else {
+			format!("{}.{}", self.points[parent].path, name)
+		};
+		self.points.push(Point {
+			label: name.to_string(),
+			path,
+			index: self.points[owner].index,
+			isreturn: false,
+			scope: Some(scope),
+			value,
+			binding: Some((owner, steps)),
+		});
+		self.scopes[scope].points.push(id);
+		self.scopes[scope].labels.insert(name.to_string(), id);
+	}
+
+	// the pattern and matched value of an unlabeled point taking a
+	// value apart
+	pub fn destructures(&self, id: usize) -> Option<(&'a AST, &'a AST)> {
+		let point = &self.points[id];
+		if !point.label.is_empty() {
+			return None;
+		}
+		destructuring(point.value)
+	}
+
+	// scope created for a Graph node of the program
+	pub fn graph(&self, ast: &AST) -> Option<usize> {
+		self.graphs.get(&(ast as *const AST)).copied()
+	}
+
+	// id of a Point node of the program
+	pub fn point(&self, ast: &AST) -> Option<usize> {
+		self.nodes.get(&(ast as *const AST)).copied()
+	}
+
+	// the name a bare point of a variant payload declares, `r` in
+	// `Circle{r}`
+	pub fn field(&self, id: usize) -> Option<&str> {
+		let point = &self.points[id];
+		let owner = &self.points[self.scopes[point.scope?].owner];
+		let union = is_type_name(&owner.label) && variants(owner.value).is_some();
+		match point.value {
+			AST::Ref(name)
+				if union
+					&& point.label.is_empty()
+					&& !is_type_name(name)
+					&& number::from_name(name).is_none() =>
+			{
+				Some(name)
+			}
+			_ => None,
+		}
+	}
+
+	// the nearest point visible from `scope` under `name`
+	pub fn resolve(
+		&self,
+		scope: Option<usize>,
+		name: &str,
+	) -> Option<usize> {
+		let mut current = scope;
+		while let Some(id) = current {
+			if let Some(point) = self.lookup(id, name, &mut vec![]) {
+				return Some(point);
+			}
+			current = self.scopes[id].parent;
+		}
+		None
+	}
+
+	// every point visible from `scope` under `name`, nearest first
+	pub fn candidates(&self, scope: Option<usize>, name: &str) -> Vec<usize> {
+		self.visible(scope, name, false)
+	}
+
+	// the union point declaring the variant `name`, if one is visible
+	pub fn variant(&self, scope: Option<usize>, name: &str) -> Option<usize> {
+		self.visible(scope, name, true).first().copied()
+	}
+
+	fn visible(
+		&self,
+		scope: Option<usize>,
+		name: &str,
+		variants: bool,
+	) -> Vec<usize> {
+		let mut found = vec![];
+		let mut current = scope;
+		while let Some(id) = current {
+			self.collect(id, name, variants, &mut vec![], &mut found);
+			current = self.scopes[id].parent;
+		}
+		found
+	}
+
+	fn collect(
+		&self,
+		scope: usize,
+		name: &str,
+		variants: bool,
+		seen: &mut Vec<usize>,
+		found: &mut Vec<usize>,
+	) {
+		if seen.contains(&scope) {
+			return;
+		}
+		seen.push(scope);
+		let names = if variants {
+			&self.scopes[scope].variants
+		} else {
+			&self.scopes[scope].labels
+		};
+		if let Some(point) = names.get(name) {
+			if !found.contains(point) {
+				found.push(*point);
+			}
+		}
+		for target in &self.scopes[scope].connections {
+			let parent = self.scopes[scope].parent;
+			if let Some(other) = self.graph_of(parent, target) {
+				self.collect(other, name, variants, seen, found);
+			}
+		}
+	}
+
+	// a label in `scope` itself or in one of its connections
+	fn lookup(
+		&self,
+		scope: usize,
+		name: &str,
+		seen: &mut Vec<usize>,
+	) -> Option<usize> {
+		if seen.contains(&scope) {
+			return None;
+		}
+		seen.push(scope);
+		if let Some(point) = self.scopes[scope].labels.get(name) {
+			return Some(*point);
+		}
+		for target in &self.scopes[scope].connections {
+			let parent = self.scopes[scope].parent;
+			if let Some(other) = self.graph_of(parent, target) {
+				if let Some(point) = self.lookup(other, name, seen) {
+					return Some(point);
+				}
+			}
+		}
+		None
+	}
+
+	// the member `name` of the graph denoted by `ast`
+	pub fn member(
+		&self,
+		scope: Option<usize>,
+		ast: &AST,
+		name: &str,
+	) -> Option<usize> {
+		self.member_seen(scope, ast, name, &mut vec![])
+	}
+
+	// the point a Ref or Select chain denotes, if statically known
+	pub fn target(&self, scope: Option<usize>, ast: &AST) -> Option<usize> {
+		self.target_seen(scope, ast, &mut vec![])
+	}
+
+	// the scope of the graph literal an expression evaluates to, if any
+	pub fn graph_of(
+		&self,
+		scope: Option<usize>,
+		ast: &AST,
+	) -> Option<usize> {
+		self.graph_of_seen(scope, ast, &mut vec![])
+	}
+
+	fn member_seen(
+		&self,
+		scope: Option<usize>,
+		ast: &AST,
+		name: &str,
+		seen: &mut Vec<usize>,
+	) -> Option<usize> {
+		let graph = self.graph_of_seen(scope, ast, seen)?;
+		if let Some(i) = position(name) {
+			return self.scopes[graph].points.get(i).copied();
+		}
+		self.lookup(graph, name, &mut vec![])
+	}
+
+	fn target_seen(
+		&self,
+		scope: Option<usize>,
+		ast: &AST,
+		seen: &mut Vec<usize>,
+	) -> Option<usize> {
+		match ast {
+			AST::Ref(name) => self.resolve(scope, name),
+			AST::Op2(Name::Select, left, right) => {
+				let name = selector(right)?;
+				self.member_seen(scope, left, name, seen)
+			}
+			_ => None,
+		}
+	}
+
+	fn graph_of_seen(
+		&self,
+		scope: Option<usize>,
+		ast: &AST,
+		seen: &mut Vec<usize>,
+	) -> Option<usize> {
+		match ast {
+			AST::Graph(_) => self.graph(ast),
+			AST::Ref(_) | AST::Op2(Name::Select, _, _) => {
+				let point = self.target_seen(scope, ast, seen)?;
+				if seen.contains(&point) {
+					return None;
+				}
+				seen.push(point);
+				let p = &self.points[point];
+				self.graph_of_seen(p.scope, p.value, seen)
+			}
+			_ => None,
+		}
+	}
+}
@@ -0,0 +1,767 @@
+use super::parser::AST;
+use super::reactor::Reactor;
+use super::reducer::{Reducer, Value};
+use super::reduction;
+use super::scope::Table;
+use super::typer::Type;
+use std::collections::VecDeque;
+use std::fmt;
+
+// Streams are values that change over time. A stream value only
+// describes where its values come from; the Scheduler runs the streams
+// of a program against a virtual clock, so time moves only when it is
+// advanced and every run emits the same values at the same times.
+// Times and durations are whole milliseconds.
+//
+// Sources start at time 0: `interval(100ms)` emits 0 at once, 1 at
+// 100ms and so on, as `sequence` emits its first item at once. Values
+// due at the same time are emitted by point, and within a stream in the
+// order they arrived. A combinator of two streams takes the left one's
+// value first when both arrive at once.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stream {
+	Interval(u64),             // 0, 1, 2.. one every period, from time 0
+	After(u64, Box<Value>),    // one value once the delay has passed
+	Sequence(Vec<Value>, u64), // the items a period apart, from time 0
+	Map(Box<Stream>, Box<Value>),    // each value through a function
+	Filter(Box<Stream>, Box<Value>), // the values a function accepts
+	Zip(Box<Stream>, Box<Stream>),   // the nth values of both, paired
+	Merge(Box<Stream>, Box<Stream>), // the values of both
+	Combine(Box<Stream>, Box<Stream>), // the latest of both, on either
+	Debounce(Box<Stream>, u64), // a value once none follows for a while
+	Throttle(Box<Stream>, u64), // a value, then none for a while
+	Sample(Box<Stream>, u64),   // the latest new value once a period
+	Buffer(Box<Stream>, u64),   // the values of each period together
+	Distinct(Box<Stream>),      // values unlike the one before
+	Take(Box<Stream>, u64),     // the first values only
+	Skip(Box<Stream>, u64),     // all but the first values
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+	Interval,
+	After,
+	Sequence,
+}
+
+pub fn from_name(name: &str) -> Option<Source> {
+	match name {
+		"interval" => Some(Source::Interval),
+		"after" => Some(Source::After),
+		"sequence" => Some(Source::Sequence),
+		_ => None,
+	}
+}
+
+pub fn name(s: Source) -> &'static str {
+	match s {
+		Source::Interval => "interval",
+		Source::After => "after",
+		Source::Sequence => "sequence",
+	}
+}
+
+pub fn parameters(s: Source) -> &'static [&'static str] {
+	match s {
+		Source::Interval => &["period"],
+		Source::After => &["delay", "value"],
+		Source::Sequence => &["items", "period"],
+	}
+}
+
+// the stream a source makes of its arguments, in parameter order
+pub fn source(s: Source, args: Vec<Value>) -> Result<Stream, String> {
+	let mut args = args.into_iter();
+	let mut next = || args.next().unwrap_or(Value::Nothing);
+	match s {
+		Source::Interval => match duration(next())? {
+			0 => Err("InvalidDuration: interval of 0".to_string()),
+			period => Ok(Stream::Interval(period)),
+		},
+		Source::After => {
+			let delay = duration(next())?;
+			Ok(Stream::After(delay, Box::new(next())))
+		}
+		Source::Sequence => match (next(), duration(next())?) {
+			(Value::Array(items) | Value::Tuple(items), period) => {
+				Ok(Stream::Sequence(items, period))
+			}
+			(other, _) => Err(format!("NotACollection: {}", other)),
+		},
+	}
+}
+
+// `latest{clicks, 0}` is the last value the stream of the point
+// `clicks` emitted, or 0 until it emitted one. The Scheduler gives each
+// value to the reactor as it is emitted, so the points reading it are
+// computed again on the tick.
+pub const LATEST: &str = "latest";
+pub const LATEST_PARAMETERS: &[&str] = &["stream", "initial"];
+
+pub struct Latest<'a> {
+	pub scope: Option<usize>, // scope of the stream and the initial value
+	pub stream: &'a AST,
+	pub initial: &'a AST,
+	pub target: usize, // the stream point read
+}
+
+// the latest value an expression reads, if it reads one; `at` is the
+// path of the point holding it, for error messages
+pub fn latest<'a>(
+	table: &Table,
+	scope: Option<usize>,
+	ast: &'a AST,
+	at: &str,
+) -> Result<Option<Latest<'a>>, String> {
+	let argument = match ast {
+		AST::Rep(original, argument) => match &**original {
+			AST::Ref(name)
+				if name == LATEST && table.resolve(scope, name).is_none() =>
+			{
+				argument
+			}
+			_ => return Ok(None),
+		},
+		_ => return Ok(None),
+	};
+	let (scope, args) = reduction::expressions(
+		table, scope, argument, LATEST, LATEST_PARAMETERS, at,
+	)?;
+	let target = table.target(scope, args[0]).ok_or(format!(
+		"InvalidLatest: {} of an expression in {}",
+		LATEST, at
+	))?;
+	Ok(Some(Latest {
+		scope,
+		stream: args[0],
+		initial: args[1],
+		target,
+	}))
+}
+
+// combinators of streams other than the reducers `map`, `filter` and
+// `zip`, which also take streams
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combinator {
+	Merge,
+	Combine,
+	Debounce,
+	Throttle,
+	Sample,
+	Buffer,
+	Distinct,
+	Take,
+	Skip,
+}
+
+pub fn combinator_from_name(name: &str) -> Option<Combinator> {
+	match name {
+		"merge" => Some(Combinator::Merge),
+		"combine" => Some(Combinator::Combine),
+		"debounce" => Some(Combinator::Debounce),
+		"throttle" => Some(Combinator::Throttle),
+		"sample" => Some(Combinator::Sample),
+		"buffer" => Some(Combinator::Buffer),
+		"distinct" => Some(Combinator::Distinct),
+		"take" => Some(Combinator::Take),
+		"skip" => Some(Combinator::Skip),
+		_ => None,
+	}
+}
+
+pub fn combinator_name(c: Combinator) -> &'static str {
+	match c {
+		Combinator::Merge => "merge",
+		Combinator::Combine => "combine",
+		Combinator::Debounce => "debounce",
+		Combinator::Throttle => "throttle",
+		Combinator::Sample => "sample",
+		Combinator::Buffer => "buffer",
+		Combinator::Distinct => "distinct",
+		Combinator::Take => "take",
+		Combinator::Skip => "skip",
+	}
+}
+
+pub fn combinator_parameters(c: Combinator) -> &'static [&'static str] {
+	match c {
+		Combinator::Merge | Combinator::Combine => &["stream", "other"],
+		Combinator::Debounce
+		| Combinator::Throttle
+		| Combinator::Sample
+		| Combinator::Buffer => &["stream", "period"],
+		Combinator::Distinct => &["stream"],
+		Combinator::Take | Combinator::Skip => &["stream", "count"],
+	}
+}
+
+// the stream a combinator makes of its arguments, in parameter order,
+// or Nothing when a stream it takes is missing
+pub fn combinator(
+	c: Combinator,
+	args: Vec<Value>,
+) -> Result<Option<Stream>, String> {
+	let mut args = args.into_iter();
+	let from = match args.next() {
+		Some(Value::Stream(stream)) => Box::new(stream),
+		Some(Value::Nothing) | None => return Ok(None),
+		Some(other) => return Err(format!("NotAStream: {}", other)),
+	};
+	let arg = args.next().unwrap_or(Value::Nothing);
+	Ok(Some(match c {
+		Combinator::Merge | Combinator::Combine => {
+			let other = match arg {
+				Value::Stream(stream) => Box::new(stream),
+				Value::Nothing => return Ok(None),
+				other => return Err(format!("NotAStream: {}", other)),
+			};
+			match c {
+				Combinator::Merge => Stream::Merge(from, other),
+				_ => Stream::Combine(from, other),
+			}
+		}
+		Combinator::Debounce => Stream::Debounce(from, duration(arg)?),
+		Combinator::Throttle => Stream::Throttle(from, duration(arg)?),
+		Combinator::Sample => match duration(arg)? {
+			0 => return Err("InvalidDuration: sample of 0".to_string()),
+			period => Stream::Sample(from, period),
+		},
+		Combinator::Buffer => match duration(arg)? {
+			0 => return Err("InvalidDuration: buffer of 0".to_string()),
+			period => Stream::Buffer(from, period),
+		},
+		Combinator::Distinct => Stream::Distinct(from),
+		Combinator::Take => Stream::Take(from, count(arg)?),
+		Combinator::Skip => Stream::Skip(from, count(arg)?),
+	}))
+}
+
+fn duration(v: Value) -> Result<u64, String> {
+	match v {
+		Value::Integer(ms) if ms >= 0 && ms <= u64::MAX as i128 => Ok(ms as u64),
+		other => Err(format!("InvalidDuration: {}", other)),
+	}
+}
+
+fn count(v: Value) -> Result<u64, String> {
+	match v {
+		Value::Integer(n) if n >= 0 && n <= u64::MAX as i128 => Ok(n as u64),
+		other => Err(format!("InvalidCount: {}", other)),
+	}
+}
+
+// a value emitted by the stream of a point
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tick {
+	pub time: u64,
+	pub point: usize,
+	pub value: Value,
+}
+
+pub struct Scheduler<'a> {
+	pub reactor: Reactor<'a>,
+	pub now: u64,
+	running: Vec<(usize, Node)>, // stream points, in point order
+}
+
+impl<'a> Scheduler<'a> {
+	// evaluates the program and starts every stream point at time 0
+	pub fn new(ast: &'a AST) -> Result<Self, String> {
+		let mut reactor = Reactor::new(ast)?;
+		let reducer = &mut reactor.reducer;
+		let mut running = vec![];
+		for id in 0..reducer.types.len() {
+			if !matches!(reducer.types[id], Type::Stream(_))
+				|| !declared(reducer, id)
+			{
+				continue;
+			}
+			// streams made from parameters have no value yet
+			if let Value::Stream(stream) = reducer.point(id)? {
+				running.push((id, Node::new(&stream)));
+			}
+		}
+		Ok(Scheduler {
+			reactor,
+			now: 0,
+			running,
+		})
+	}
+
+	// moves the clock forward, emitting every value due on the way by
+	// time, and by point for values due at the same time; each value is
+	// a tick of the reactor
+	pub fn advance(&mut self, duration: u64) -> Result<Vec<Tick>, String> {
+		let until = self.now.saturating_add(duration);
+		let mut ticks = vec![];
+		loop {
+			let mut due = None;
+			for (i, (point, node)) in self.running.iter_mut().enumerate() {
+				node.fill(&mut self.reactor.reducer, *point, until)?;
+				if let Some((time, _)) = node.ready {
+					if due.is_none_or(|(t, _)| time < t) {
+						due = Some((time, i));
+					}
+				}
+			}
+			let i = match due {
+				Some((_, i)) => i,
+				None => break,
+			};
+			let (point, node) = &mut self.running[i];
+			if let Some((time, value)) = node.ready.take() {
+				let point = *point;
+				self.now = time;
+				self.reactor.emit(point, value.clone())?;
+				ticks.push(Tick { time, point, value });
+			}
+		}
+		self.now = until;
+		Ok(ticks)
+	}
+
+	// the last value the stream of a point emitted
+	pub fn latest(&self, id: usize) -> Option<&Value> {
+		self.reactor.reducer.latest.get(&id)
+	}
+}
+
+// whether a point is declared in the graphs of the program rather
+// than given to a built-in or a replication, `a` in `merge{a, b}`
+fn declared(r: &Reducer, id: usize) -> bool {
+	let table = &r.deps.table;
+	let mut scope = table.points[id].scope;
+	while let Some(s) = scope {
+		let owner = &table.points[table.scopes[s].owner];
+		if !matches!(owner.value, AST::Graph(_)) {
+			return false;
+		}
+		scope = table.scopes[s].parent;
+	}
+	true
+}
+
+// a running stream, its next value once known and the state of its
+// stage
+struct Node {
+	ready: Option<(u64, Value)>,
+	stage: Stage,
+}
+
+enum Stage {
+	Interval(u64, u64), // period, values emitted
+	After(u64, Option<Value>),
+	Sequence(VecDeque<Value>, u64, u64), // items left, period, emitted
+	Map(Box<Node>, Value),
+	Filter(Box<Node>, Value),
+	Zip(Box<Node>, Box<Node>, VecDeque<(u64, Value)>, VecDeque<(u64, Value)>),
+	Merge(Box<Node>, Box<Node>),
+	Combine(Box<Node>, Box<Node>, Option<Value>, Option<Value>),
+	Debounce(Box<Node>, u64, Option<(u64, Value)>), // value waiting
+	Throttle(Box<Node>, u64, u64), // period, time values pass again
+	// period, next sample if any is left, new value
+	Sample(Box<Node>, u64, Option<u64>, Option<Value>),
+	Buffer(Box<Node>, u64, Option<u64>, Vec<Value>), // and window
+	Distinct(Box<Node>, Option<Value>),
+	Take(Box<Node>, u64),
+	Skip(Box<Node>, u64),
+}
+
+impl Node {
+	fn new(stream: &Stream) -> Node {
+		let node = |s: &Stream| Box::new(Node::new(s));
+		let stage = match stream {
+			Stream::Interval(period) => Stage::Interval(*period, 0),
+			Stream::After(delay, value) => {
+				Stage::After(*delay, Some((**value).clone()))
+			}
+			Stream::Sequence(items, period) => {
+				Stage::Sequence(items.iter().cloned().collect(), *period, 0)
+			}
+			Stream::Map(from, f) => Stage::Map(node(from), (**f).clone()),
+			Stream::Filter(from, f) => Stage::Filter(node(from), (**f).clone()),
+			Stream::Zip(l, r) => {
+				Stage::Zip(node(l), node(r), VecDeque::new(), VecDeque::new())
+			}
+			Stream::Merge(l, r) => Stage::Merge(node(l), node(r)),
+			Stream::Combine(l, r) => Stage::Combine(node(l), node(r), None, None),
+			Stream::Debounce(from, period) => {
+				Stage::Debounce(node(from), *period, None)
+			}
+			Stream::Throttle(from, period) => {
+				Stage::Throttle(node(from), *period, 0)
+			}
+			Stream::Sample(from, period) => {
+				Stage::Sample(node(from), *period, Some(*period), None)
+			}
+			Stream::Buffer(from, period) => {
+				Stage::Buffer(node(from), *period, Some(*period), vec![])
+			}
+			Stream::Distinct(from) => Stage::Distinct(node(from), None),
+			Stream::Take(from, n) => Stage::Take(node(from), *n),
+			Stream::Skip(from, n) => Stage::Skip(node(from), *n),
+		};
+		Node { ready: None, stage }
+	}
+
+	// the next value, if one is due by `until`; values of the stages
+	// before are looked at only as far as that
+	fn fill(
+		&mut self,
+		r: &mut Reducer,
+		point: usize,
+		until: u64,
+	) -> Result<(), String> {
+		if self.ready.is_some() {
+			return Ok(());
+		}
+		self.ready = match &mut self.stage {
+			Stage::Interval(period, emitted) => {
+				match period.checked_mul(*emitted) {
+					Some(time) if time <= until => {
+						*emitted += 1;
+						Some((time, Value::Integer(*emitted as i128 - 1)))
+					}
+					_ => None,
+				}
+			}
+			Stage::After(delay, value) if *delay <= until => {
+				value.take().map(|v| (*delay, v))
+			}
+			Stage::After(_, _) => None,
+			Stage::Sequence(items, period, emitted) => {
+				match period.checked_mul(*emitted) {
+					Some(time) if time <= until && !items.is_empty() => {
+						*emitted += 1;
+						items.pop_front().map(|v| (time, v))
+					}
+					_ => None,
+				}
+			}
+			Stage::Map(from, f) => match from.take(r, point, until)? {
+				Some((time, v)) => Some((time, r.apply_for(point, f, vec![v])?)),
+				None => None,
+			},
+			Stage::Filter(from, f) => loop {
+				match from.take(r, point, until)? {
+					Some((time, v)) => {
+						let keep = r.apply_for(point, f, vec![v.clone()])?;
+						if keep == Value::Boolean(true) {
+							break Some((time, v));
+						}
+					}
+					None => break None,
+				}
+			},
+			Stage::Zip(left, right, lefts, rights) => loop {
+				if !lefts.is_empty() && !rights.is_empty() {
+					let (a, x) = lefts.pop_front().unwrap_or((0, Value::Nothing));
+					let (b, y) = rights.pop_front().unwrap_or((0, Value::Nothing));
+					break Some((a.max(b), Value::Tuple(vec![x, y])));
+				}
+				let from = if lefts.is_empty() { &mut *left } else { &mut *right };
+				match from.take(r, point, until)? {
+					Some(e) if lefts.is_empty() => lefts.push_back(e),
+					Some(e) => rights.push_back(e),
+					None => break None,
+				}
+			},
+			Stage::Merge(left, right) => {
+				left.fill(r, point, until)?;
+				right.fill(r, point, until)?;
+				match (&left.ready, &right.ready) {
+					(Some((a, _)), Some((b, _))) if b < a => right.ready.take(),
+					(Some(_), _) => left.ready.take(),
+					_ => right.ready.take(),
+				}
+			}
+			Stage::Combine(left, right, x, y) => loop {
+				left.fill(r, point, until)?;
+				right.fill(r, point, until)?;
+				let time = match (&left.ready, &right.ready) {
+					(Some((a, _)), Some((b, _))) if b < a => {
+						right.ready.take().map(|(t, v)| {
+							*y = Some(v);
+							t
+						})
+					}
+					(Some(_), _) => left.ready.take().map(|(t, v)| {
+						*x = Some(v);
+						t
+					}),
+					_ => right.ready.take().map(|(t, v)| {
+						*y = Some(v);
+						t
+					}),
+				};
+				match (time, &x, &y) {
+					(Some(t), Some(a), Some(b)) => {
+						break Some((t, Value::Tuple(vec![a.clone(), b.clone()])))
+					}
+					(Some(_), _, _) => {}
+					(None, _, _) => break None,
+				}
+			},
+			Stage::Debounce(from, period, waiting) => loop {
+				from.fill(r, point, until)?;
+				let next = from.ready.as_ref().map(|(t, _)| *t);
+				// a value arriving before the quiet period is over
+				// replaces the one waiting
+				if let Some((time, _)) = waiting {
+					let due = time.saturating_add(*period);
+					if due <= until && next.is_none_or(|t| t >= due) {
+						break waiting.take().map(|(_, v)| (due, v));
+					}
+				}
+				match from.ready.take() {
+					Some(e) => *waiting = Some(e),
+					None => break None,
+				}
+			},
+			Stage::Throttle(from, period, open) => loop {
+				match from.take(r, point, until)? {
+					Some((time, v)) if time >= *open => {
+						*open = time.saturating_add(*period);
+						break Some((time, v));
+					}
+					Some(_) => {}
+					None => break None,
+				}
+			},
+			Stage::Sample(from, period, next, fresh) => loop {
+				let time = match *next {
+					Some(time) if time <= until => time,
+					_ => break None,
+				};
+				// values arriving at the time of a sample are in it
+				match from.take(r, point, time)? {
+					Some((_, v)) => *fresh = Some(v),
+					None => {
+						*next = time.checked_add(*period);
+						if let Some(v) = fresh.take() {
+							break Some((time, v));
+						}
+						if from.done() {
+							*next = None;
+						}
+					}
+				}
+			},
+			Stage::Buffer(from, period, next, window) => loop {
+				let time = match *next {
+					Some(time) if time <= until => time,
+					_ => break None,
+				};
+				match from.take(r, point, time)? {
+					Some((_, v)) => window.push(v),
+					// a source that has ended ends the buffer once its
+					// last window is out
+					None if from.done() && window.is_empty() => {
+						*next = None;
+						break None;
+					}
+					None => {
+						*next = time.checked_add(*period);
+						break Some((time, Value::Array(std::mem::take(window))));
+					}
+				}
+			},
+			Stage::Distinct(from, last) => loop {
+				match from.take(r, point, until)? {
+					Some((time, v)) if last.as_ref() != Some(&v) => {
+						*last = Some(v.clone());
+						break Some((time, v));
+					}
+					Some(_) => {}
+					None => break None,
+				}
+			},
+			Stage::Take(from, left) if *left > 0 => {
+				let next = from.take(r, point, until)?;
+				if next.is_some() {
+					*left -= 1;
+				}
+				next
+			}
+			Stage::Take(_, _) => None,
+			Stage::Skip(from, left) => loop {
+				match from.take(r, point, until)? {
+					Some(_) if *left > 0 => *left -= 1,
+					next => break next,
+				}
+			},
+		};
+		Ok(())
+	}
+
+	// whether no value is left to come
+	fn done(&self) -> bool {
+		if self.ready.is_some() {
+			return false;
+		}
+		match &self.stage {
+			Stage::Interval(_, _) => false,
+			Stage::After(_, value) => value.is_none(),
+			Stage::Sequence(items, _, _) => items.is_empty(),
+			Stage::Map(from, _)
+			| Stage::Filter(from, _)
+			| Stage::Throttle(from, _, _)
+			| Stage::Distinct(from, _)
+			| Stage::Skip(from, _) => from.done(),
+			Stage::Zip(left, right, lefts, rights) => {
+				left.done() && lefts.is_empty()
+					|| right.done() && rights.is_empty()
+			}
+			Stage::Merge(left, right) | Stage::Combine(left, right, _, _) => {
+				left.done() && right.done()
+			}
+			Stage::Debounce(from, _, waiting) => {
+				from.done() && waiting.is_none()
+			}
+			Stage::Sample(_, _, next, _) | Stage::Buffer(_, _, next, _) => {
+				next.is_none()
+			}
+			Stage::Take(from, left) => *left == 0 || from.done(),
+		}
+	}
+
+	fn take(
+		&mut self,
+		r: &mut Reducer,
+		point: usize,
+		until: u64,
+	) -> Result<Option<(u64, Value)>, String> {
+		self.fill(r, point, until)?;
+		Ok(self.ready.take())
+	}
+}
+
+impl fmt::Display for Stream {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Stream::Interval(period) => write!(f, "interval({}ms)", period),
+			Stream::After(delay, value) => {
+				write!(f, "after{{{}ms, {}}}", delay, value)
+			}
+			Stream::Sequence(items, period) => {
+				let items: Vec<String> =
+					items.iter().map(|v| v.to_string()).collect();
+				write!(f, "sequence{{[{}], {}ms}}", items.join(", "), period)
+			}
+			Stream::Map(from, _) => write!(f, "map{{{}, function}}", from),
+			Stream::Filter(from, _) => write!(f, "filter{{{}, function}}", from),
+			Stream::Zip(l, r) => write!(f, "zip{{{}, {}}}", l, r),
+			Stream::Merge(l, r) => write!(f, "merge{{{}, {}}}", l, r),
+			Stream::Combine(l, r) => write!(f, "combine{{{}, {}}}", l, r),
+			Stream::Debounce(from, period) => {
+				write!(f, "debounce{{{}, {}ms}}", from, period)
+			}
+			Stream::Throttle(from, period) => {
+				write!(f, "throttle{{{}, {}ms}}", from, period)
+			}
+			Stream::Sample(from, period) => {
+				write!(f, "sample{{{}, {}ms}}", from, period)
+			}
+			Stream::Buffer(from, period) => {
+				write!(f, "buffer{{{}, {}ms}}", from, period)
+			}
+			Stream::Distinct(from) => write!(f, "distinct({})", from),
+			Stream::Take(from, n) => write!(f, "take{{{}, {}}}", from, n),
+			Stream::Skip(from, n) => write!(f, "skip{{{}, {}}}", from, n),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compiler::{parser, tokenizer};
+
+	// the time, point path and value of each tick, advancing the clock
+	// by each of `steps` in turn
+	fn run(source: &str, steps: &[u64]) -> Vec<String> {
+		let ast = parser::parser(&tokenizer::tokenizer(source)).unwrap();
+		let mut scheduler = Scheduler::new(&ast).unwrap();
+		let mut ticks = vec![];
+		for step in steps {
+			for tick in scheduler.advance(*step).unwrap() {
+				let points = &scheduler.reactor.reducer.deps.table.points;
+				let path = &points[tick.point].path;
+				ticks.push(format!("{} {} {}", tick.time, path, tick.value));
+			}
+		}
+		ticks
+	}
+
+	// the value of the point at `path` as the reactor has it
+	fn value(scheduler: &mut Scheduler, path: &str) -> String {
+		let id = scheduler.reactor.find(path).unwrap();
+		scheduler.reactor.value(id).unwrap().to_string()
+	}
+
+	#[test]
+	fn advance_emits_the_values_due_in_time_order() {
+		let source = "a: interval(100ms)\nb: sequence{[1, 2], 150ms}";
+		assert_eq!(
+			run(source, &[300]),
+			["0 a 0", "0 b 1", "100 a 1", "150 b 2", "200 a 2", "300 a 3"]
+		);
+	}
+
+	#[test]
+	fn advance_continues_where_it_stopped() {
+		let source = "a: interval(100ms)";
+		assert_eq!(
+			run(source, &[50, 50, 100]),
+			["0 a 0", "100 a 1", "200 a 2"]
+		);
+		let once = "a: after{250ms, \"ready\"}";
+		assert_eq!(run(once, &[200, 100, 1000]), ["250 a \"ready\""]);
+	}
+
+	#[test]
+	fn advance_saturates_the_clock() {
+		let source = "a: sequence{[1, 2], 10ms}";
+		assert_eq!(run(source, &[u64::MAX, u64::MAX]), ["0 a 1", "10 a 2"]);
+	}
+
+	#[test]
+	fn buffers_and_samples_end_with_their_source() {
+		let source = "s: sequence{[1, 2, 3], 30ms}\nb: buffer{s, 50ms}";
+		assert_eq!(
+			run(source, &[u64::MAX - 100]),
+			["0 s 1", "30 s 2", "50 b [1, 2]", "60 s 3", "100 b [3]"]
+		);
+		let source = "s: sequence{[1, 2, 3], 30ms}\nm: sample{s, 40ms}";
+		assert_eq!(
+			run(source, &[u64::MAX, u64::MAX]),
+			["0 s 1", "30 s 2", "40 m 2", "60 s 3", "80 m 3"]
+		);
+	}
+
+	#[test]
+	fn sources_start_at_time_zero() {
+		let source = "a: interval(100ms)\nb: sequence{[5], 100ms}";
+		assert_eq!(run(source, &[0]), ["0 a 0", "0 b 5"]);
+		assert_eq!(run(source, &[99]), ["0 a 0", "0 b 5"]);
+	}
+
+	#[test]
+	fn ticks_reach_the_points_reading_the_latest_value() {
+		let source = "clicks: sequence{[1, 2, 3], 10ms}\n\
+			last: latest{clicks, 0}\n\
+			doubled: last * 2";
+		let ast = parser::parser(&tokenizer::tokenizer(source)).unwrap();
+		let mut scheduler = Scheduler::new(&ast).unwrap();
+		assert_eq!(value(&mut scheduler, "last"), "0");
+		scheduler.advance(0).unwrap();
+		assert_eq!(value(&mut scheduler, "last"), "1");
+		assert_eq!(value(&mut scheduler, "doubled"), "2");
+		scheduler.advance(20).unwrap();
+		assert_eq!(value(&mut scheduler, "last"), "3");
+		assert_eq!(value(&mut scheduler, "doubled"), "6");
+		let clicks = scheduler.reactor.find("clicks").unwrap();
+		assert_eq!(scheduler.latest(clicks), Some(&Value::Integer(3)));
+	}
+}
@@ -32,6 +32,7 @@ pub enum Name {
 	Comma,
 	//
 	Pattern,
+	Coalesce,
 	//
 	Or,
 	And,
@@ -53,6 +54,7 @@ pub enum Name {
 	Length,
 	//
 	Select,
+	Optional,
 	// Parent, // shouldn't be needed
 	Range,
 	//
@@ -65,6 +67,7 @@ pub enum Name {
 	Integer,
 	Decimal,
 	Boolean,
+	Duration,
 	//
 	ParenLF,
 	ParenRT,
@@ -91,7 +94,7 @@ pub struct Token {
 	pub meta: Meta,
 }
 
-pub fn tokenizer(input: &String) -> Vec<Token> {
+pub fn tokenizer(input: &str) -> Vec<Token> {
 	lazy_static! {
 		static ref SPEC: Vec<(Kind, Name, Regex)> =
 			vec![
@@ -109,6 +112,11 @@ pub fn tokenizer(input: &String) -> Vec<Token> {
 				(Kind::Label, Name::Key, Regex::new(r"^[A-Za-z'_][A-Za-z0-9'_]*:").unwrap()),
 
 
+				// `'` does not end a word for `\b`, so names going on past
+				// a boolean, `true'x`, are taken first
+				(Kind::Label, Name::Ref, Regex::new(r"^(false|true)[A-Za-z0-9'_]+").unwrap()),
+				(Kind::Number, Name::Boolean, Regex::new(r"^(false|true)").unwrap()),
+
 				(Kind::Label, Name::Ref, Regex::new(r"^[A-Za-z'_][A-Za-z0-9'_]*").unwrap()),
 				(Kind::Label, Name::Arrow, Regex::new(r"^(->|→)").unwrap()),
 
@@ -122,9 +130,9 @@ pub fn tokenizer(input: &String) -> Vec<Token> {
 				// (Kind::Binary, Name::Range, Regex::new(r"^[0-9]*\.\.[0-9]+").unwrap()),
 
 				// (Kind::Number, Name::Number, Regex::new(r"^[0-9]+\.[0-9]*").unwrap()),
+				(Kind::Number, Name::Duration, Regex::new(r"^[0-9']+(ms|s|m|h)\b").unwrap()),
 				(Kind::Number, Name::Decimal, Regex::new(r"^[0-9']+\.[0-9']+").unwrap()),
 				(Kind::Number, Name::Integer, Regex::new(r"^[0-9']+").unwrap()),
-				(Kind::Number, Name::Boolean, Regex::new(r"^(false|true)\b").unwrap()),
 
 				// Reserved Words
 				(Kind::Reserved, Name::Reserved, Regex::new(r"^if\b").unwrap()),
@@ -134,6 +142,8 @@ pub fn tokenizer(input: &String) -> Vec<Token> {
 				// (Kind::Bool, Name::False, Regex::new(r"^false\b").unwrap()),
 				// Operators
 				(Kind::Binary, Name::Pattern, Regex::new(r"^[~]").unwrap()),
+				(Kind::Binary, Name::Coalesce, Regex::new(r"^\?\?").unwrap()),
+				(Kind::Select, Name::Optional, Regex::new(r"^\?\.").unwrap()),
 				(Kind::Binary, Name::Or, Regex::new(r"^[|]").unwrap()),
 				(Kind::Binary, Name::And, Regex::new(r"^[&]").unwrap()),
 				(Kind::Binary, Name::Eq, Regex::new(r"^((==)|(=))").unwrap()),
@@ -188,97 +198,113 @@ pub fn tokenizer(input: &String) -> Vec<Token> {
 
 	'outer: while cursor < length {
 		for (kind, name, re) in &SPEC[..] {
-			match re.find(&input[cursor..]) {
-				Some(mat) => {
-					let token_text = &input[cursor..cursor + mat.end()];
-
-					let t = Token {
-						of: Of {
-							kind: *kind,
-							name: *name,
-						},
-						meta: Meta {
-							line,
-							text: token_text.to_string(),
-						},
-					};
-
-					match (kind, name) {
-						(Kind::Skip, _) => {}
-						(Kind::Stop, Name::Comma) => {
-							if last_token_was_newline {
-								tokens.pop();
-							}
-							if !last_token_was_comma {
-								tokens.push(t);
-								last_token_was_operator = false;
-								last_token_was_comma = true;
-								last_token_was_newline = false;
-							}
-						}
-						(Kind::Stop, Name::Newline) => {
-							if !last_token_was_operator
-								&& !last_token_was_comma && !last_token_was_newline
-								&& !skip_initial_newlines
-							{
-								tokens.push(t);
-								last_token_was_operator = false;
-								last_token_was_comma = false;
-								last_token_was_newline = true;
-							}
-							line += 1;
+			if let Some(mat) = re.find(&input[cursor..]) {
+				let token_text = &input[cursor..cursor + mat.end()];
+
+				let t = Token {
+					of: Of {
+						kind: *kind,
+						name: *name,
+					},
+					meta: Meta {
+						line,
+						text: token_text.to_string(),
+					},
+				};
+
+				match (kind, name) {
+					(Kind::Skip, _) => {}
+					(Kind::Stop, Name::Comma) => {
+						if last_token_was_newline {
+							tokens.pop();
 						}
-
-						(Kind::Paren, Name::ParenLF)
-						| (Kind::Squaren, Name::SquarenLF)
-						| (Kind::Bracket, Name::BracketLF) => {
+						if !last_token_was_comma {
 							tokens.push(t);
-							last_token_was_operator = true;
-							last_token_was_comma = false;
+							last_token_was_operator = false;
+							last_token_was_comma = true;
 							last_token_was_newline = false;
 						}
-
-						(Kind::Paren, Name::ParenRT)
-						| (Kind::Squaren, Name::SquarenRT)
-						| (Kind::Bracket, Name::BracketRT) => {
-							if last_token_was_newline {
-								tokens.pop();
-							}
+					}
+					(Kind::Stop, Name::Newline) => {
+						if !last_token_was_operator
+							&& !last_token_was_comma && !last_token_was_newline
+							&& !skip_initial_newlines
+						{
 							tokens.push(t);
 							last_token_was_operator = false;
 							last_token_was_comma = false;
-							last_token_was_newline = false;
+							last_token_was_newline = true;
 						}
+						line += 1;
+					}
 
-						(Kind::Binary, _)
-						| (Kind::Unary, _)
-						| (Kind::Select, _)
-						| (Kind::Label, Name::Key)
-						| (Kind::Label, Name::Arrow) => {
-							if last_token_was_newline {
-								tokens.pop();
-							}
-							tokens.push(t);
-							last_token_was_operator = true;
-							last_token_was_comma = false;
-							last_token_was_newline = false;
+					(Kind::Paren, Name::ParenLF)
+					| (Kind::Squaren, Name::SquarenLF)
+					| (Kind::Bracket, Name::BracketLF) => {
+						tokens.push(t);
+						last_token_was_operator = true;
+						last_token_was_comma = false;
+						last_token_was_newline = false;
+					}
+
+					(Kind::Paren, Name::ParenRT)
+					| (Kind::Squaren, Name::SquarenRT)
+					| (Kind::Bracket, Name::BracketRT) => {
+						if last_token_was_newline {
+							tokens.pop();
 						}
-						_ => {
-							tokens.push(t);
+						tokens.push(t);
+						last_token_was_operator = false;
+						last_token_was_comma = false;
+						last_token_was_newline = false;
+					}
 
-							last_token_was_operator = false;
-							last_token_was_comma = false;
-							last_token_was_newline = false;
-							skip_initial_newlines = false;
+					(Kind::Binary, _)
+					| (Kind::Unary, _)
+					| (Kind::Select, _)
+					| (Kind::Label, Name::Key)
+					| (Kind::Label, Name::Arrow) => {
+						if last_token_was_newline {
+							tokens.pop();
 						}
+						tokens.push(t);
+						last_token_was_operator = true;
+						last_token_was_comma = false;
+						last_token_was_newline = false;
 					}
+					_ => {
+						tokens.push(t);
 
-					cursor += mat.end();
-					continue 'outer;
+						last_token_was_operator = false;
+						last_token_was_comma = false;
+						last_token_was_newline = false;
+						skip_initial_newlines = false;
+					}
 				}
-				None => {}
+
+				cursor += mat.end();
+				continue 'outer;
 			}
 		}
 	}
 	tokens
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn names(input: &str) -> Vec<Name> {
+		tokenizer(input).iter().map(|t| t.of.name).collect()
+	}
+
+	#[test]
+	fn booleans_end_at_a_word_boundary() {
+		assert_eq!(names("true"), vec![Name::Boolean]);
+		let or = vec![Name::Boolean, Name::Or, Name::Boolean];
+		assert_eq!(names("false|true"), or);
+		assert_eq!(names("true'x"), vec![Name::Ref]);
+		assert_eq!(names("false_"), vec![Name::Ref]);
+		assert_eq!(names("trueish"), vec![Name::Ref]);
+	}
+}
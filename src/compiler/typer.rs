@@ -53,28 +53,2721 @@ namespace: {
 }
 
 */
-use super::parser::{Number, AST};
+use super::delay::{self, Delay};
+use super::number;
+use super::parser::{
+	self, is_type_name, position, selector, variants, Number, Precedence,
+	AST,
+};
+use super::reduction::{self, Reduction};
+use super::scope::{scope, Step, Table};
+use super::stream::{self, Combinator, Latest, Source};
 use super::tokenizer::Name;
 use std::collections::HashMap;
+use std::fmt;
 
-// enum Data {
-// 	V(Vec<Node>),
-// 	B(Box<Node>),
-// }
-
-// pub struct Node {
-// 	label: String,
-// 	position: i32,
-// 	kind: Primitive,
-// 	namespace: HashMap<String, Node>,
-// 	data: Data,
-// }
-
-pub fn typer(ast: &AST) -> Result<AST, String> {
-	// let mut namespace: HashMap<String, Primitive> = HashMap::new();
-
-	// // Review some books.
-	// book_reviews
-	// 	.insert("Adventures of Huckleberry Finn".to_string(), Primitive::Nothing);
-	Err("nothing to see".to_string())
+// the label return points carry in a graph type, `{a: i32 -> i32}`
+const RESULT: &str = "->";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+	Nothing,
+	Boolean,
+	Number(Number),
+	Range(Number, f64, f64), // a number within bounds, `0..100`
+	String,
+	Array(Box<Type>),
+	Maybe(Box<Type>), // a value or Nothing, `u16 | Nothing`
+	Tuple(Vec<Type>),
+	// unlabeled points have an empty label, an open graph carries a row
+	// variable standing for the labeled points not known yet
+	Graph(Vec<(String, Type)>, Option<usize>),
+	// one of several variants, each with the points of its payload
+	Union(Vec<(String, Type)>),
+	Stream(Box<Type>), // values arriving over time
+	Var(usize),
+	Type(Box<Type>), // an expression naming a type, e.g. `i32`
+}
+
+// The typed tree mirrors the AST with the resolved type of every node.
+#[derive(Debug, Clone)]
+pub struct Typed {
+	pub of: Type,
+	pub node: Node,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+	Nothing,
+	Number(Number, String),
+	String(String),
+	Graph(Vec<Typed>),
+	Array(Vec<Typed>),
+	Tuple(Vec<Typed>),
+	Point(String, u16, bool, Box<Typed>),
+	Op2(Name, Box<Typed>, Box<Typed>),
+	Op1(Name, Box<Typed>),
+	Ref(String),
+	Arg(Box<Typed>),
+	Rep(Box<Typed>, Box<Typed>),
+}
+
+pub fn typer(ast: &AST) -> Result<Typed, String> {
+	let mut checker = checker(ast)?;
+	checker.point(0)?;
+	let mut typed = checker.build(ast);
+	// each point of the program names its variables from 'a
+	typed.name(&mut vec![]);
+	if let Node::Point(_, _, _, value) = &mut typed.node {
+		if let Node::Graph(items) = &mut value.node {
+			for item in items {
+				item.name(&mut vec![]);
+			}
+		}
+	}
+	Ok(typed)
+}
+
+// the type of every point, by its id in the scope table
+pub fn types(ast: &AST) -> Result<Vec<Type>, String> {
+	let mut checker = checker(ast)?;
+	checker.point(0)?;
+	Ok(checker
+		.types
+		.iter()
+		.map(|t| match t {
+			Some(t) => checker.resolve(t),
+			None => Type::Nothing,
+		})
+		.collect())
+}
+
+fn checker(ast: &AST) -> Result<Checker<'_>, String> {
+	let table = scope(ast)?;
+	Ok(Checker {
+		types: vec![None; table.points.len()],
+		schemes: vec![vec![]; table.points.len()],
+		depth: vec![0; table.points.len()],
+		nodes: HashMap::new(),
+		pending: vec![],
+		delays: vec![],
+		subst: vec![],
+		levels: vec![],
+		table,
+	})
+}
+
+struct Checker<'a> {
+	table: Table<'a>,
+	types: Vec<Option<Type>>, // per point, once checked
+	schemes: Vec<Vec<usize>>, // per point, variables free for each use
+	depth: Vec<usize>,        // per point, length of `pending` in check
+	nodes: HashMap<*const AST, Type>, // per expression
+	pending: Vec<usize>,      // points being checked
+	delays: Vec<(usize, Type)>, // prev of points being checked
+	subst: Vec<Option<Type>>, // per type variable
+	levels: Vec<usize>, // per type variable, the depth that owns it
+}
+
+impl Checker<'_> {
+	fn point(&mut self, id: usize) -> Result<Type, String> {
+		if let Some(t) = &self.types[id] {
+			return Ok(t.clone());
+		}
+		if self.pending.contains(&id) {
+			return Err(format!(
+				"CyclicReference: {}",
+				self.table.points[id].path
+			));
+		}
+		self.pending.push(id);
+		self.depth[id] = self.pending.len();
+		let point = &self.table.points[id];
+		let (scope, value) = (point.scope, point.value);
+		let param = !point.label.is_empty() && !is_type_name(&point.label);
+		let union = is_type_name(&point.label) && variants(value).is_some();
+		let binding = point.binding.clone();
+		let range = match number::range(value) {
+			Some((n, lo, hi)) if is_type_name(&point.label) => {
+				Some(Type::Range(n, lo, hi))
+			}
+			_ => None,
+		};
+		let taken = match (binding, self.table.destructures(id)) {
+			// a name bound by a pattern, `a` in `[a, b] ~ pair`
+			(Some((owner, steps)), _) => {
+				let mut t = self.point(owner)?;
+				for step in &steps {
+					t = self.part(&t, step)?;
+				}
+				Some(t)
+			}
+			(None, Some((pattern, matched))) => {
+				let t = self.expression(scope, matched)?;
+				self.destructure(pattern, &t)?;
+				Some(t)
+			}
+			(None, None) => None,
+		};
+		let t = match (value, range, taken) {
+			(_, _, Some(t)) => {
+				self.nodes.insert(value as *const AST, t.clone());
+				t
+			}
+			(AST::Nothing, _, _) => {
+				// a parameter belongs to the graph holding it, so it
+				// stays monomorphic until that graph is complete
+				let owner = scope.map(|s| self.table.scopes[s].owner);
+				let level = match owner {
+					Some(o) if self.pending.contains(&o) => self.depth[o],
+					_ => self.pending.len(),
+				};
+				let mut t = self.fresh_at(level);
+				// a capitalized parameter stands for a type, `T:` in
+				// `Pair {T:, first: T, second: T}`
+				if is_type_name(&self.table.points[id].label) {
+					t = Type::Type(Box::new(t));
+				}
+				self.nodes.insert(value as *const AST, t.clone());
+				t
+			}
+			// `Percent: 0..100` declares a bounded number
+			(_, Some(range), _) => {
+				let t = Type::Type(Box::new(range));
+				self.nodes.insert(value as *const AST, t.clone());
+				t
+			}
+			_ if union => {
+				let t = self.union(value)?;
+				self.nodes.insert(value as *const AST, t.clone());
+				t
+			}
+			_ => {
+				let t = self.expression(scope, value)?;
+				// a lowercase point holding a type is a parameter of
+				// that type, `b: Point`
+				if param && self.names_type(scope, value, &t) {
+					Type::Type(Box::new(self.declared(&t)?))
+				} else {
+					t
+				}
+			}
+		};
+		// prev of this point read while it was checked
+		let (delays, others) = std::mem::take(&mut self.delays)
+			.into_iter()
+			.partition(|(target, _)| *target == id);
+		self.delays = others;
+		for (_, delayed) in delays {
+			self.unify(&t, &delayed)?;
+		}
+		self.pending.pop();
+		if !matches!(value, AST::Nothing) {
+			let mut free = vec![];
+			self.free(&t, &mut free);
+			let depth = self.pending.len();
+			free.retain(|v| self.levels[*v] > depth);
+			self.schemes[id] = free;
+		}
+		self.types[id] = Some(t.clone());
+		Ok(t)
+	}
+
+	// the type of a point at one use, with its free variables renewed
+	fn instance(&mut self, id: usize) -> Result<Type, String> {
+		let t = self.point(id)?;
+		let t = match self.resolve(&t) {
+			Type::Type(t) if !is_type_name(&self.table.points[id].label) => {
+				*t
+			}
+			_ => t,
+		};
+		if self.schemes[id].is_empty() {
+			return Ok(t);
+		}
+		let mut fresh = HashMap::new();
+		for v in self.schemes[id].clone() {
+			fresh.insert(v, self.fresh_var());
+		}
+		Ok(self.rename(&self.resolve(&t), &fresh))
+	}
+
+	fn rename(&self, t: &Type, fresh: &HashMap<usize, usize>) -> Type {
+		match t {
+			Type::Var(v) => Type::Var(*fresh.get(v).unwrap_or(v)),
+			Type::Array(item) => Type::Array(Box::new(self.rename(item, fresh))),
+			Type::Maybe(t) => Type::Maybe(Box::new(self.rename(t, fresh))),
+			Type::Stream(t) => Type::Stream(Box::new(self.rename(t, fresh))),
+			Type::Type(t) => Type::Type(Box::new(self.rename(t, fresh))),
+			Type::Tuple(items) => Type::Tuple(
+				items.iter().map(|t| self.rename(t, fresh)).collect(),
+			),
+			Type::Graph(fields, tail) => Type::Graph(
+				fields
+					.iter()
+					.map(|(l, t)| (l.clone(), self.rename(t, fresh)))
+					.collect(),
+				tail.map(|v| *fresh.get(&v).unwrap_or(&v)),
+			),
+			Type::Union(cases) => Type::Union(
+				cases
+					.iter()
+					.map(|(n, t)| (n.clone(), self.rename(t, fresh)))
+					.collect(),
+			),
+			_ => t.clone(),
+		}
+	}
+
+	fn expression(
+		&mut self,
+		scope: Option<usize>,
+		ast: &AST,
+	) -> Result<Type, String> {
+		let t = self.synth(scope, ast)?;
+		self.nodes.insert(ast as *const AST, t.clone());
+		Ok(t)
+	}
+
+	fn synth(
+		&mut self,
+		scope: Option<usize>,
+		ast: &AST,
+	) -> Result<Type, String> {
+		if let Some(d) = delay::delay(&self.table, scope, ast, self.at())? {
+			return self.delay(&d);
+		}
+		if let Some(l) = stream::latest(&self.table, scope, ast, self.at())? {
+			return self.latest(&l);
+		}
+		match ast {
+			AST::Nothing => Ok(Type::Nothing),
+			AST::Number(Number::Boolean, _) => Ok(Type::Boolean),
+			AST::Number(n, _) => Ok(Type::Number(*n)),
+			AST::String(_) => Ok(Type::String),
+			AST::Array(list) => {
+				let mut item = self.fresh();
+				for exp in list {
+					let t = self.expression(scope, exp)?;
+					item = self.unify(&item, &t)?;
+				}
+				Ok(Type::Array(Box::new(item)))
+			}
+			AST::Tuple(list) => {
+				let mut items = vec![];
+				for exp in list {
+					items.push(self.expression(scope, exp)?);
+				}
+				Ok(Type::Tuple(items))
+			}
+			AST::Graph(_) => self.graph(ast),
+			AST::Point(_, _, _, value) | AST::Arg(value) => {
+				self.expression(scope, value)
+			}
+			AST::Ref(name) => match self.table.resolve(scope, name) {
+				Some(id) => self.instance(id),
+				None => match builtin(name) {
+					Some(t) => Ok(Type::Type(Box::new(t))),
+					None => match self.variant(scope, ast) {
+						Some(id) => self.construct(scope, name, id, None),
+						None => Err(format!(
+							"UnresolvedReference: {} in {}",
+							name,
+							self.at()
+						)),
+					},
+				},
+			},
+			AST::Op2(Name::Select, left, right) => {
+				self.select(scope, left, right)
+			}
+			AST::Op2(Name::Optional, left, right) => {
+				self.optional(scope, left, right)
+			}
+			AST::Op2(Name::Coalesce, left, right) => {
+				let l = self.expression(scope, left)?;
+				let r = self.expression(scope, right)?;
+				self.coalesce(&l, &r)
+			}
+			AST::Op2(Name::Pattern, left, right) => {
+				if let Some((n, lo, hi)) = number::range(left) {
+					let t = Type::Range(n, lo, hi);
+					let declared = Type::Type(Box::new(t.clone()));
+					self.nodes.insert(&**left as *const AST, declared);
+					return self.bounded(scope, &t, right);
+				}
+				let l = self.expression(scope, left)?;
+				if let (Type::Union(cases), AST::Graph(_)) =
+					(self.resolve(&l), &**right)
+				{
+					return self.matches(scope, &cases, right);
+				}
+				let declared = self.declared(&l)?;
+				if let Type::Range(_, _, _) = declared {
+					return self.bounded(scope, &declared, right);
+				}
+				let t = self.expression(scope, right)?;
+				self.assign(&declared, &t)?;
+				self.fits(right, &declared)?;
+				Ok(declared)
+			}
+			AST::Op2(op, left, right) => {
+				let l = self.expression(scope, left)?;
+				let r = self.expression(scope, right)?;
+				self.fits(left, &r)?;
+				self.fits(right, &l)?;
+				match self.overload(scope, *op, &[l.clone(), r.clone()])? {
+					Some(t) => Ok(t),
+					None => self.binary(*op, &l, &r),
+				}
+			}
+			AST::Op1(op, value) => {
+				let t = self.expression(scope, value)?;
+				match self.overload(scope, *op, std::slice::from_ref(&t))? {
+					Some(t) => Ok(t),
+					None => self.unary(*op, &t),
+				}
+			}
+			AST::Rep(original, argument) => {
+				if let Some(n) = self.conversion(scope, original) {
+					let t = self.expression(scope, argument)?;
+					return match self.resolve(&t) {
+						Type::Number(_) | Type::Boolean | Type::Var(_) => {
+							Ok(Type::Number(n))
+						}
+						other => Err(format!(
+							"InvalidConversion: {} to {} in {}",
+							other,
+							number::name(n),
+							self.at()
+						)),
+					};
+				}
+				if let (AST::Ref(name), Some(id)) =
+					(&**original, self.variant(scope, original))
+				{
+					return self.construct(scope, name, id, Some(argument));
+				}
+				if let Some(r) = self.reduction(scope, original) {
+					return self.reduce(scope, r, argument);
+				}
+				if let Some(s) = self.source(scope, original) {
+					return self.stream(scope, s, argument);
+				}
+				if let Some(c) = self.combinator(scope, original) {
+					return self.combine(scope, c, argument);
+				}
+				self.replicate(scope, original, argument)
+			}
+		}
+	}
+
+	// `prev{count, 0}` has the type of `count` and of the initial
+	// value, `<count` may be Nothing
+	fn delay(&mut self, d: &Delay) -> Result<Type, String> {
+		let target = delay::target(&self.table, d, self.at())?;
+		let t = if self.pending.contains(&target) {
+			let t = self.fresh();
+			self.delays.push((target, t.clone()));
+			t
+		} else {
+			self.instance(target)?
+		};
+		self.nodes.insert(d.value as *const AST, t.clone());
+		match d.initial {
+			Some(initial) => {
+				let i = self.expression(d.scope, initial)?;
+				self.unify(&t, &i)
+			}
+			None => Ok(self.maybe(&t)),
+		}
+	}
+
+	// `latest{clicks, 0}` has the type of the values of `clicks` and of
+	// the initial value
+	fn latest(&mut self, l: &Latest) -> Result<Type, String> {
+		let t = self.instance(l.target)?;
+		self.nodes.insert(l.stream as *const AST, t.clone());
+		let item = self.streamed(&t)?;
+		let i = self.expression(l.scope, l.initial)?;
+		self.unify(&item, &i)
+	}
+
+	// `volume: 0..100 ~ input` takes any number, a literal is checked
+	// against the bounds here and other values when they arrive
+	fn bounded(
+		&mut self,
+		scope: Option<usize>,
+		declared: &Type,
+		ast: &AST,
+	) -> Result<Type, String> {
+		let t = self.expression(scope, ast)?;
+		self.assign(declared, &t)?;
+		let (n, lo, hi) = match declared {
+			Type::Range(n, lo, hi) => (*n, *lo, *hi),
+			_ => return Ok(declared.clone()),
+		};
+		if let Some((_, value)) = number::literal(ast) {
+			if value < lo || value > hi {
+				return Err(format!(
+					"RangeViolation: {} outside {} in {}",
+					value,
+					declared,
+					self.at()
+				));
+			}
+		}
+		match self.resolve(&t) {
+			Type::Number(m) | Type::Range(m, _, _) => {
+				Ok(Type::Range(number::join(n, m).unwrap_or(n), lo, hi))
+			}
+			_ => Ok(declared.clone()),
+		}
+	}
+
+	// `Shape: Circle{r: f64} | Rect{w, h} | Empty` declares a union, a
+	// bare name in a payload is a point of any type
+	fn union(&mut self, value: &AST) -> Result<Type, String> {
+		let mut cases = vec![];
+		for (name, payload) in variants(value).unwrap_or_default() {
+			let mut fields = vec![];
+			if let Some(payload) = payload {
+				let graph = match self.table.graph(payload) {
+					Some(graph) => graph,
+					None => return Err("UnscopedGraph".to_string()),
+				};
+				for id in self.table.scopes[graph].points.clone() {
+					let field = match self.table.field(id) {
+						Some(name) => {
+							let name = name.to_string();
+							let t = self.fresh();
+							let value = self.table.points[id].value;
+							self.nodes.insert(value as *const AST, t.clone());
+							self.types[id] = Some(t.clone());
+							(name, t)
+						}
+						None => {
+							let label = self.table.points[id].label.clone();
+							(label, self.point(id)?)
+						}
+					};
+					fields.push(field);
+				}
+			}
+			let payload = self.declared(&Type::Graph(fields, None))?;
+			cases.push((name.to_string(), payload));
+		}
+		Ok(Type::Type(Box::new(Type::Union(cases))))
+	}
+
+	// the union point declaring the variant an unresolved name denotes
+	fn variant(&self, scope: Option<usize>, ast: &AST) -> Option<usize> {
+		match ast {
+			AST::Ref(name) if self.table.resolve(scope, name).is_none() => {
+				self.table.variant(scope, name)
+			}
+			_ => None,
+		}
+	}
+
+	// a value of the union holding variant `name`, every point of the
+	// payload must be given
+	fn construct(
+		&mut self,
+		scope: Option<usize>,
+		name: &str,
+		id: usize,
+		argument: Option<&AST>,
+	) -> Result<Type, String> {
+		let t = self.instance(id)?;
+		let union = match self.resolve(&t) {
+			Type::Type(union) => *union,
+			other => {
+				return Err(format!(
+					"InvalidVariant: {} of {} in {}",
+					name,
+					other,
+					self.at()
+				))
+			}
+		};
+		let fields = match &union {
+			Type::Union(cases) => match cases.iter().find(|(n, _)| n == name)
+			{
+				Some((_, Type::Graph(fields, _))) => fields.clone(),
+				_ => vec![],
+			},
+			_ => vec![],
+		};
+		let args = match argument {
+			Some(argument) => self.arguments(scope, argument)?,
+			None => vec![],
+		};
+		let mut given = vec![false; fields.len()];
+		let mut index = 0;
+		for (label, arg) in &args {
+			let at = if label.is_empty() {
+				index += 1;
+				Some(index - 1).filter(|i| *i < fields.len())
+			} else {
+				fields.iter().position(|(l, _)| l == label)
+			};
+			match at {
+				Some(i) => {
+					self.assign(&fields[i].1, arg)?;
+					given[i] = true;
+				}
+				None if label.is_empty() => {
+					return Err(format!(
+						"TooManyArguments: {} given to {} in {}",
+						args.len(),
+						name,
+						self.at()
+					))
+				}
+				None => {
+					return Err(format!(
+						"UnknownLabel: {} on {} in {}",
+						label,
+						name,
+						self.at()
+					))
+				}
+			}
+		}
+		if let Some(i) = given.iter().position(|given| !given) {
+			return Err(format!(
+				"MissingArgument: {} for {} in {}",
+				fields[i].0,
+				name,
+				self.at()
+			));
+		}
+		Ok(self.resolve(&union))
+	}
+
+	// `shape ~ {Circle: {r: -> r * r}, _: 0}` takes the case of the
+	// variant held, every variant needs a case unless `_` is given
+	fn matches(
+		&mut self,
+		scope: Option<usize>,
+		cases: &[(String, Type)],
+		ast: &AST,
+	) -> Result<Type, String> {
+		self.expression(scope, ast)?;
+		let graph = match self.table.graph(ast) {
+			Some(graph) => graph,
+			None => return Err("UnscopedGraph".to_string()),
+		};
+		let mut result = self.fresh();
+		let mut covered = vec![];
+		let mut wildcard = false;
+		for id in self.table.scopes[graph].points.clone() {
+			let label = self.table.points[id].label.clone();
+			let t = if label == "_" {
+				wildcard = true;
+				self.instance(id)?
+			} else {
+				let payload = match cases.iter().find(|(n, _)| *n == label) {
+					Some((_, payload)) => payload,
+					None => {
+						return Err(format!(
+							"UnknownVariant: {} of {} in {}",
+							label,
+							Type::Union(cases.to_vec()),
+							self.at()
+						))
+					}
+				};
+				covered.push(label);
+				let fields = match self.resolve(payload) {
+					Type::Graph(fields, _) => fields,
+					_ => vec![],
+				};
+				match self.handler(id, &fields)? {
+					Some(t) => t,
+					None => self.instance(id)?,
+				}
+			};
+			result = self.unify(&result, &t)?;
+		}
+		let missing: Vec<&str> = cases
+			.iter()
+			.map(|(n, _)| &n[..])
+			.filter(|n| !covered.iter().any(|c| c == n))
+			.collect();
+		if !wildcard && !missing.is_empty() {
+			return Err(format!(
+				"NonExhaustive: missing {} in {}",
+				missing.join(", "),
+				self.at()
+			));
+		}
+		Ok(result)
+	}
+
+	// the result of a case whose parameters name points of the payload
+	fn handler(
+		&mut self,
+		id: usize,
+		payload: &[(String, Type)],
+	) -> Result<Option<Type>, String> {
+		let (params, result) = match self.signature(id) {
+			Some(signature) => signature,
+			None => return Ok(None),
+		};
+		let t = self.instance(id)?;
+		let fields = match self.resolve(&t) {
+			Type::Graph(fields, _) => fields,
+			_ => return Ok(None),
+		};
+		for param in params {
+			let (label, field) = &fields[param];
+			match payload.iter().find(|(l, _)| l == label) {
+				Some((_, value)) => self.parameter(field, value)?,
+				None => {
+					return Err(format!(
+						"UnknownLabel: {} on {} in {}",
+						label,
+						Type::Graph(payload.to_vec(), None),
+						self.at()
+					))
+				}
+			}
+		}
+		Ok(Some(self.results(&fields, &result)))
+	}
+
+	// a pattern takes apart values of its shape, arrays and tuples by
+	// position and graphs by label
+	fn destructure(&mut self, pattern: &AST, t: &Type) -> Result<(), String> {
+		match pattern {
+			AST::Array(items) | AST::Tuple(items) => {
+				let rest = items
+					.iter()
+					.position(|item| matches!(item, AST::Op1(Name::Range, _)));
+				if rest.is_some_and(|i| i + 1 != items.len()) {
+					return Err(format!(
+						"InvalidPattern: rest before the last item in {}",
+						self.at()
+					));
+				}
+				if let Type::Tuple(types) = self.resolve(t) {
+					let fixed = rest.unwrap_or(items.len());
+					if fixed > types.len() || rest.is_none() && fixed < types.len()
+					{
+						return Err(format!(
+							"PatternMismatch: {} items against {} in {}",
+							fixed,
+							self.resolve(t),
+							self.at()
+						));
+					}
+				}
+				for (i, item) in items.iter().enumerate() {
+					let (item, step) = match item {
+						AST::Op1(Name::Range, item) => (&**item, Step::Rest(i)),
+						_ => (item, Step::Index(i)),
+					};
+					let part = self.part(t, &step)?;
+					self.destructure(item, &part)?;
+				}
+				Ok(())
+			}
+			AST::Graph(items) => {
+				for item in items {
+					match item {
+						AST::Point(label, _, _, value) if !label.is_empty() => {
+							let step = Step::Label(label.clone());
+							let part = self.part(t, &step)?;
+							self.destructure(value, &part)?;
+						}
+						AST::Point(_, _, _, value)
+							if matches!(**value, AST::Op1(Name::Range, _)) => {}
+						_ => {
+							return Err(format!(
+								"InvalidPattern: unlabeled point in {}",
+								self.at()
+							))
+						}
+					}
+				}
+				Ok(())
+			}
+			AST::Nothing | AST::Ref(_) => Ok(()),
+			_ => Err(format!("InvalidPattern in {}", self.at())),
+		}
+	}
+
+	// one step into a value taken apart by a pattern
+	fn part(&mut self, t: &Type, step: &Step) -> Result<Type, String> {
+		let found = self.resolve(t);
+		match (found, step) {
+			(Type::Var(_), Step::Index(_) | Step::Rest(_)) => {
+				let item = self.fresh();
+				self.unify(t, &Type::Array(Box::new(item)))?;
+				self.part(t, step)
+			}
+			(Type::Array(item), Step::Index(_)) => Ok(*item),
+			(t @ Type::Array(_), Step::Rest(_)) => Ok(t),
+			(Type::Tuple(items), Step::Index(i)) => match items.get(*i) {
+				Some(t) => Ok(t.clone()),
+				None => Err(format!(
+					"PatternMismatch: no item {} in {} in {}",
+					i,
+					Type::Tuple(items.clone()),
+					self.at()
+				)),
+			},
+			(Type::Tuple(items), Step::Rest(i)) => {
+				Ok(Type::Tuple(items.into_iter().skip(*i).collect()))
+			}
+			(Type::Graph(_, _) | Type::Var(_), Step::Label(label)) => {
+				self.field(t, label)
+			}
+			(Type::Graph(_, _) | Type::Var(_), Step::Others(named)) => {
+				// the named points are taken first, the rest keeps any
+				// points still unknown
+				for label in named {
+					self.field(t, label)?;
+				}
+				match self.resolve(t) {
+					Type::Graph(fields, tail) => Ok(Type::Graph(
+						fields
+							.into_iter()
+							.filter(|(l, _)| !named.contains(l))
+							.collect(),
+						tail,
+					)),
+					other => Ok(other),
+				}
+			}
+			(Type::Maybe(_), _) => Err(self.nothing(t)),
+			(found, Step::Index(_) | Step::Rest(_)) => Err(format!(
+				"PatternMismatch: expected an array found {} in {}",
+				found,
+				self.at()
+			)),
+			(found, _) => Err(format!(
+				"PatternMismatch: expected a graph found {} in {}",
+				found,
+				self.at()
+			)),
+		}
+	}
+
+	// the type an annotation stands for, points of a graph type that
+	// hold plain values declare the type of that value
+	fn declared(&self, t: &Type) -> Result<Type, String> {
+		match self.resolve(t) {
+			Type::Type(t) => Ok(*t),
+			Type::Array(item) => {
+				Ok(Type::Array(Box::new(self.declared(&item)?)))
+			}
+			Type::Tuple(items) => {
+				let mut types = vec![];
+				for t in &items {
+					types.push(self.declared(t)?);
+				}
+				Ok(Type::Tuple(types))
+			}
+			Type::Graph(fields, tail) => {
+				let mut types = vec![];
+				for (label, t) in fields {
+					let t = match self.resolve(&t) {
+						// type members stay types
+						Type::Type(_) if is_type_name(&label) => t,
+						Type::Type(t) => *t,
+						Type::Graph(_, _) => self.declared(&t)?,
+						t => t,
+					};
+					types.push((label, t));
+				}
+				Ok(Type::Graph(types, tail))
+			}
+			other => Err(format!("NotAType: {} in {}", other, self.at())),
+		}
+	}
+
+	// a value of type `t` where `declared` is expected, numbers may only
+	// widen into the declared type
+	fn assign(&mut self, declared: &Type, t: &Type) -> Result<(), String> {
+		match (self.resolve(declared), self.resolve(t)) {
+			(Type::Number(d), Type::Number(n)) => {
+				if number::widens(n, d) {
+					Ok(())
+				} else {
+					Err(self.annotation(declared, t))
+				}
+			}
+			(Type::Array(d), Type::Array(n)) => self.assign(&d, &n),
+			(Type::Range(_, lo, hi), Type::Range(_, a, b)) => {
+				if lo <= a && b <= hi {
+					Ok(())
+				} else {
+					Err(self.annotation(declared, t))
+				}
+			}
+			(Type::Range(d, _, _), Type::Number(n)) => {
+				match number::join(d, n) {
+					Ok(_) => Ok(()),
+					Err(_) => Err(self.annotation(declared, t)),
+				}
+			}
+			(Type::Maybe(d), Type::Maybe(n)) => self.assign(&d, &n),
+			(Type::Maybe(_), Type::Nothing) => Ok(()),
+			(Type::Maybe(d), _) => self.assign(&d, t),
+			(Type::Graph(d, None), Type::Graph(n, None))
+				if d.len() == n.len() =>
+			{
+				for (i, (label, d)) in d.iter().enumerate() {
+					let n = if positional(label) {
+						n.get(i)
+					} else {
+						n.iter().find(|(l, _)| l == label)
+					};
+					match n {
+						Some((_, n)) => self.assign(d, n)?,
+						None => return Err(self.annotation(declared, t)),
+					}
+				}
+				Ok(())
+			}
+			_ => match self.unify(declared, t) {
+				Ok(_) => Ok(()),
+				Err(_) => Err(self.annotation(declared, t)),
+			},
+		}
+	}
+
+	fn annotation(&self, declared: &Type, t: &Type) -> String {
+		format!(
+			"AnnotationMismatch: declared {} found {} in {}",
+			self.resolve(declared),
+			self.resolve(t),
+			self.at()
+		)
+	}
+
+	// the expression is a type, or refers to a capitalized point
+	// holding one
+	fn names_type(&self, scope: Option<usize>, ast: &AST, t: &Type) -> bool {
+		self.is_type(t)
+			|| match self.table.target(scope, ast) {
+				Some(id) => {
+					is_type_name(&self.table.points[id].label)
+						&& matches!(self.resolve(t), Type::Type(_))
+				}
+				None => false,
+			}
+	}
+
+	// a type, or a structure made only of types, `[T]` or `Pair(i32)`
+	fn is_type(&self, t: &Type) -> bool {
+		match self.resolve(t) {
+			Type::Type(_) => true,
+			Type::Array(item) => self.is_type(&item),
+			Type::Graph(fields, None) => {
+				!fields.is_empty() && fields.iter().all(|(_, t)| self.is_type(t))
+			}
+			_ => false,
+		}
+	}
+
+	// the graph is a declaration, `Point` or `Pair(i32)`
+	fn declaration(&self, scope: Option<usize>, ast: &AST) -> bool {
+		match ast {
+			AST::Rep(original, _) => self.declaration(scope, original),
+			_ => match self.table.target(scope, ast) {
+				Some(id) => is_type_name(&self.table.points[id].label),
+				None => false,
+			},
+		}
+	}
+
+	// a capitalized point holding a type, taken by type arguments only
+	fn type_parameter(&self, field: &(String, Type)) -> bool {
+		is_type_name(&field.0) && matches!(self.resolve(&field.1), Type::Type(_))
+	}
+
+	// `u16(x)` converts explicitly unless `u16` names a point in scope
+	fn conversion(&self, scope: Option<usize>, ast: &AST) -> Option<Number> {
+		match ast {
+			AST::Ref(name) if self.table.resolve(scope, name).is_none() => {
+				number::from_name(name)
+			}
+			_ => None,
+		}
+	}
+
+	fn reduction(&self, scope: Option<usize>, ast: &AST) -> Option<Reduction> {
+		match ast {
+			AST::Ref(name) if self.table.resolve(scope, name).is_none() => {
+				reduction::from_name(name)
+			}
+			_ => None,
+		}
+	}
+
+	fn source(&self, scope: Option<usize>, ast: &AST) -> Option<Source> {
+		match ast {
+			AST::Ref(name) if self.table.resolve(scope, name).is_none() => {
+				stream::from_name(name)
+			}
+			_ => None,
+		}
+	}
+
+	fn combinator(&self, scope: Option<usize>, ast: &AST) -> Option<Combinator> {
+		match ast {
+			AST::Ref(name) if self.table.resolve(scope, name).is_none() => {
+				stream::combinator_from_name(name)
+			}
+			_ => None,
+		}
+	}
+
+	// the arguments of a built-in, the points of a graph or one value
+	fn builtin_arguments(
+		&mut self,
+		scope: Option<usize>,
+		params: &[&str],
+		argument: &AST,
+	) -> Result<Vec<(String, Type)>, String> {
+		if reduction::labeled(params, argument) {
+			return self.arguments(scope, argument);
+		}
+		Ok(vec![(String::new(), self.expression(scope, argument)?)])
+	}
+
+	// `interval(100ms)`, `after{1s, "done"}`, `sequence{[1, 2], 10ms}`
+	fn stream(
+		&mut self,
+		scope: Option<usize>,
+		s: Source,
+		argument: &AST,
+	) -> Result<Type, String> {
+		let args = self.builtin_arguments(
+			scope,
+			stream::parameters(s),
+			argument,
+		)?;
+		let args = reduction::arguments(
+			stream::name(s),
+			stream::parameters(s),
+			args,
+			self.at(),
+		)?;
+		let item = match s {
+			Source::Interval => {
+				self.duration(&args[0])?;
+				Type::Number(Number::Integer)
+			}
+			Source::After => {
+				self.duration(&args[0])?;
+				args[1].clone()
+			}
+			Source::Sequence => {
+				self.duration(&args[1])?;
+				self.items(&args[0])?
+			}
+		};
+		Ok(Type::Stream(Box::new(item)))
+	}
+
+	// `merge{clicks, keys}`, `debounce{input, 300ms}`, `take{ticks, 3}`
+	fn combine(
+		&mut self,
+		scope: Option<usize>,
+		c: Combinator,
+		argument: &AST,
+	) -> Result<Type, String> {
+		let args = self.builtin_arguments(
+			scope,
+			stream::combinator_parameters(c),
+			argument,
+		)?;
+		let args = reduction::arguments(
+			stream::combinator_name(c),
+			stream::combinator_parameters(c),
+			args,
+			self.at(),
+		)?;
+		let item = self.streamed(&args[0])?;
+		let item = match c {
+			Combinator::Merge => {
+				let other = self.streamed(&args[1])?;
+				self.unify(&item, &other)?
+			}
+			Combinator::Combine => {
+				let other = self.streamed(&args[1])?;
+				Type::Tuple(vec![item, other])
+			}
+			Combinator::Debounce | Combinator::Throttle | Combinator::Sample => {
+				self.duration(&args[1])?;
+				item
+			}
+			Combinator::Buffer => {
+				self.duration(&args[1])?;
+				Type::Array(Box::new(item))
+			}
+			Combinator::Distinct => item,
+			Combinator::Take | Combinator::Skip => {
+				self.count(&args[1])?;
+				item
+			}
+		};
+		Ok(Type::Stream(Box::new(item)))
+	}
+
+	// the type of the values of a stream
+	fn streamed(&mut self, t: &Type) -> Result<Type, String> {
+		match self.resolve(t) {
+			Type::Stream(item) => Ok(*item),
+			Type::Var(_) => {
+				let item = self.fresh();
+				self.unify(t, &Type::Stream(Box::new(item.clone())))?;
+				Ok(item)
+			}
+			Type::Maybe(_) => Err(self.nothing(t)),
+			other => Err(format!("NotAStream: {} in {}", other, self.at())),
+		}
+	}
+
+	// counts of values are whole numbers
+	fn count(&mut self, t: &Type) -> Result<(), String> {
+		let t = self.unify(t, &Type::Number(Number::Integer))?;
+		match self.resolve(&t) {
+			Type::Number(n) if number::is_float(n) => Err(format!(
+				"InvalidCount: {} in {}",
+				t,
+				self.at()
+			)),
+			_ => Ok(()),
+		}
+	}
+
+	// durations are whole numbers of milliseconds
+	fn duration(&mut self, t: &Type) -> Result<(), String> {
+		let t = self.unify(t, &Type::Number(Number::Integer))?;
+		match self.resolve(&t) {
+			Type::Number(n) if number::is_float(n) => Err(format!(
+				"InvalidDuration: {} in {}",
+				t,
+				self.at()
+			)),
+			_ => Ok(()),
+		}
+	}
+
+	// `map{xs, {x: -> x * 2}}` and the other built-in reducers
+	fn reduce(
+		&mut self,
+		scope: Option<usize>,
+		r: Reduction,
+		argument: &AST,
+	) -> Result<Type, String> {
+		let args = self.builtin_arguments(
+			scope,
+			reduction::parameters(r),
+			argument,
+		)?;
+		let args = reduction::optional_arguments(
+			reduction::name(r),
+			reduction::parameters(r),
+			reduction::required(r),
+			args,
+			self.at(),
+		)?;
+		let args: Vec<Type> = args.into_iter().flatten().collect();
+		let items = &args[0];
+		if let Type::Stream(item) = self.resolve(items) {
+			return self.streamed_reduce(r, &args, *item);
+		}
+		let item = self.items(items)?;
+		match r {
+			Reduction::Map => {
+				let t = self.apply(&args[1], &[item])?;
+				Ok(match self.resolve(items) {
+					Type::Tuple(types) => Type::Tuple(vec![t; types.len()]),
+					Type::Graph(fields, _) => Type::Graph(
+						fields.into_iter().map(|(l, _)| (l, t.clone())).collect(),
+						None,
+					),
+					_ => Type::Array(Box::new(t)),
+				})
+			}
+			Reduction::Filter => {
+				let t = self.apply(&args[1], std::slice::from_ref(&item))?;
+				self.unify(&t, &Type::Boolean)?;
+				// which points of a graph are kept is only known
+				// once it is filtered
+				Ok(match self.resolve(items) {
+					Type::Graph(_, _) => Type::Graph(vec![], Some(self.fresh_row())),
+					_ => Type::Array(Box::new(item)),
+				})
+			}
+			Reduction::Fold | Reduction::Scan => {
+				let t = self.apply(&args[2], &[args[1].clone(), item.clone()])?;
+				let t = self.unify(&args[1], &t)?;
+				if let Some(inverse) = args.get(3) {
+					let undone = self.apply(inverse, &[t.clone(), item])?;
+					self.unify(&t, &undone)?;
+				}
+				Ok(match r {
+					Reduction::Scan => Type::Array(Box::new(t)),
+					_ => t,
+				})
+			}
+			Reduction::Zip => {
+				let other = self.items(&args[1])?;
+				Ok(Type::Array(Box::new(Type::Tuple(vec![item, other]))))
+			}
+			Reduction::FlatMap => {
+				let t = self.apply(&args[1], &[item])?;
+				let t = self.items(&t)?;
+				Ok(Type::Array(Box::new(t)))
+			}
+			Reduction::Sum => self.numeric(Name::Add, &item),
+			// the smallest or largest of no items is Nothing
+			Reduction::Min | Reduction::Max => {
+				self.numeric(Name::Lt, &item)?;
+				Ok(self.maybe(&item))
+			}
+			Reduction::Any | Reduction::All => {
+				let t = self.apply(&args[1], &[item])?;
+				self.unify(&t, &Type::Boolean)
+			}
+			Reduction::Sort => {
+				self.numeric(Name::Lt, &item)?;
+				Ok(Type::Array(Box::new(item)))
+			}
+		}
+	}
+
+	// `map`, `filter` and `zip` of a stream give a stream
+	fn streamed_reduce(
+		&mut self,
+		r: Reduction,
+		args: &[Type],
+		item: Type,
+	) -> Result<Type, String> {
+		let item = match r {
+			Reduction::Map => self.apply(&args[1], &[item])?,
+			Reduction::Filter => {
+				let t = self.apply(&args[1], std::slice::from_ref(&item))?;
+				self.unify(&t, &Type::Boolean)?;
+				item
+			}
+			Reduction::Zip => {
+				let other = self.streamed(&args[1])?;
+				Type::Tuple(vec![item, other])
+			}
+			_ => {
+				return Err(format!(
+					"NotACollection: {} given to {} in {}",
+					self.resolve(&args[0]),
+					reduction::name(r),
+					self.at()
+				))
+			}
+		};
+		Ok(Type::Stream(Box::new(item)))
+	}
+
+	// the type of the items of a collection, ranges are arrays
+	fn items(&mut self, t: &Type) -> Result<Type, String> {
+		let types = match self.resolve(t) {
+			Type::Array(item) => return Ok(*item),
+			Type::Tuple(types) => types,
+			Type::Graph(fields, _) => fields.into_iter().map(|(_, t)| t).collect(),
+			Type::Var(_) => {
+				let item = self.fresh();
+				self.unify(t, &Type::Array(Box::new(item.clone())))?;
+				return Ok(item);
+			}
+			Type::Maybe(_) => return Err(self.nothing(t)),
+			other => {
+				return Err(format!(
+					"NotACollection: {} in {}",
+					other,
+					self.at()
+				))
+			}
+		};
+		let mut item = self.fresh();
+		for t in &types {
+			item = self.unify(&item, t)?;
+		}
+		Ok(item)
+	}
+
+	// the result of a function-like graph given values for its first
+	// parameters
+	fn apply(&mut self, f: &Type, args: &[Type]) -> Result<Type, String> {
+		let fields = match self.resolve(f) {
+			Type::Graph(fields, _) => fields,
+			other => {
+				return Err(format!(
+					"NotAFunction: {} in {}",
+					other,
+					self.at()
+				))
+			}
+		};
+		let (results, params): (Vec<usize>, Vec<usize>) =
+			(0..fields.len()).partition(|i| fields[*i].0 == RESULT);
+		if results.is_empty() || params.len() < args.len() {
+			return Err(format!(
+				"NotAFunction: {} given {} values in {}",
+				self.resolve(f),
+				args.len(),
+				self.at()
+			));
+		}
+		for (param, arg) in params.iter().zip(args) {
+			self.parameter(&fields[*param].1, arg)?;
+		}
+		Ok(self.results(&fields, &results))
+	}
+
+	fn graph(&mut self, ast: &AST) -> Result<Type, String> {
+		let graph = match self.table.graph(ast) {
+			Some(graph) => graph,
+			None => return Err("UnscopedGraph".to_string()),
+		};
+		let parent = self.table.scopes[graph].parent;
+		for target in self.table.scopes[graph].connections.clone() {
+			self.expression(parent, target)?;
+		}
+		let mut fields = vec![];
+		for id in self.table.scopes[graph].points.clone() {
+			let t = self.point(id)?;
+			let point = &self.table.points[id];
+			let label = if point.isreturn {
+				RESULT.to_string()
+			} else {
+				point.label.clone()
+			};
+			fields.push((label, t));
+		}
+		Ok(Type::Graph(fields, None))
+	}
+
+	fn select(
+		&mut self,
+		scope: Option<usize>,
+		left: &AST,
+		right: &AST,
+	) -> Result<Type, String> {
+		let label = match selector(right) {
+			Some(label) => label,
+			None => {
+				return Err(format!("InvalidSelector in {}", self.at()))
+			}
+		};
+		let t = self.expression(scope, left)?;
+		let t = self.field(&t, label)?;
+		self.nodes.insert(right as *const AST, t.clone());
+		Ok(t)
+	}
+
+	// `a?.b` selects through a value that may be Nothing, and may be
+	// Nothing itself
+	fn optional(
+		&mut self,
+		scope: Option<usize>,
+		left: &AST,
+		right: &AST,
+	) -> Result<Type, String> {
+		let label = match selector(right) {
+			Some(label) => label,
+			None => {
+				return Err(format!("InvalidSelector in {}", self.at()))
+			}
+		};
+		let t = self.expression(scope, left)?;
+		let t = match self.resolve(&t) {
+			Type::Maybe(t) => {
+				let t = self.field(&t, label)?;
+				self.maybe(&t)
+			}
+			_ => self.field(&t, label)?,
+		};
+		self.nodes.insert(right as *const AST, t.clone());
+		Ok(t)
+	}
+
+	// `a ?? b` is `a` when present and `b` otherwise, an unknown `a` is
+	// taken to be possibly Nothing
+	fn coalesce(&mut self, l: &Type, r: &Type) -> Result<Type, String> {
+		let t = match self.resolve(l) {
+			Type::Maybe(t) => *t,
+			// always missing, it is the default
+			Type::Nothing => return Ok(r.clone()),
+			Type::Var(_) => {
+				let t = self.fresh();
+				self.unify(l, &Type::Maybe(Box::new(t.clone())))?;
+				t
+			}
+			t => t,
+		};
+		match self.resolve(r) {
+			Type::Maybe(r) => {
+				let t = self.unify(&t, &r)?;
+				Ok(self.maybe(&t))
+			}
+			_ => self.unify(&t, r),
+		}
+	}
+
+	fn maybe(&self, t: &Type) -> Type {
+		match self.resolve(t) {
+			Type::Maybe(_) | Type::Nothing => t.clone(),
+			t => Type::Maybe(Box::new(t)),
+		}
+	}
+
+	fn nothing(&self, t: &Type) -> String {
+		format!(
+			"PossiblyNothing: {} may be Nothing in {}",
+			self.resolve(t),
+			self.at()
+		)
+	}
+
+	// the type of point `label` of a graph, an unknown or open graph
+	// learns that it has such a point; a position selects a point of a
+	// graph or tuple known to be there
+	fn field(&mut self, t: &Type, label: &str) -> Result<Type, String> {
+		if let Some(i) = position(label) {
+			let item = match self.resolve(t) {
+				Type::Graph(fields, _) => fields.get(i).map(|(_, t)| t.clone()),
+				Type::Tuple(items) => items.get(i).cloned(),
+				_ => None,
+			};
+			return item.ok_or_else(|| {
+				format!(
+					"UnknownPosition: {} on {} in {}",
+					i,
+					self.resolve(t),
+					self.at()
+				)
+			});
+		}
+		match self.resolve(t) {
+			Type::Graph(fields, tail) => {
+				if let Some((_, t)) = fields.iter().find(|(l, _)| l == label)
+				{
+					return Ok(t.clone());
+				}
+				match tail {
+					Some(row) => {
+						let t = self.fresh();
+						let rest = self.fresh_row();
+						let more = Type::Graph(
+							vec![(label.to_string(), t.clone())],
+							Some(rest),
+						);
+						self.bind(row, &more)?;
+						Ok(t)
+					}
+					None => Err(format!(
+						"UnknownLabel: {} on {} in {}",
+						label,
+						Type::Graph(fields, tail),
+						self.at()
+					)),
+				}
+			}
+			Type::Var(v) => {
+				let t = self.fresh();
+				let rest = self.fresh_row();
+				let graph = Type::Graph(
+					vec![(label.to_string(), t.clone())],
+					Some(rest),
+				);
+				self.bind(v, &graph)?;
+				Ok(t)
+			}
+			Type::Maybe(_) => Err(self.nothing(t)),
+			other => Err(format!(
+				"InvalidSelect: .{} on {} in {}",
+				label,
+				other,
+				self.at()
+			)),
+		}
+	}
+
+	// the original graph with its points overridden by the argument,
+	// points declared with a type take values of that type
+	fn replicate(
+		&mut self,
+		scope: Option<usize>,
+		original: &AST,
+		argument: &AST,
+	) -> Result<Type, String> {
+		let t = self.expression(scope, original)?;
+		// a parameter not known yet is a function-like graph taking
+		// whatever it is given, `f: v: -> f{x: v}`
+		if let Type::Var(v) = self.resolve(&t) {
+			let result = (RESULT.to_string(), self.fresh());
+			let row = self.fresh_row();
+			self.bind(v, &Type::Graph(vec![result], Some(row)))?;
+		}
+		let (mut fields, tail) = match self.resolve(&t) {
+			Type::Graph(fields, tail) => (fields, tail),
+			other => {
+				return Err(format!(
+					"InvalidReplication: {} is not a graph in {}",
+					other,
+					self.at()
+				))
+			}
+		};
+		let args = self.arguments(scope, argument)?;
+		let mut given = vec![false; fields.len()];
+		let mut index = 0;
+		for (label, arg) in &args {
+			let at = if label.is_empty() {
+				// values pass over type parameters, `Pair(i32){1, 2}`,
+				// and nothing is given to a return point
+				let value = !matches!(self.resolve(arg), Type::Type(_));
+				while index < fields.len()
+					&& (fields[index].0 == RESULT
+						|| value && self.type_parameter(&fields[index]))
+				{
+					index += 1;
+				}
+				index += 1;
+				if index > fields.len() {
+					return Err(format!(
+						"TooManyArguments: {} given to {} in {}",
+						args.len(),
+						self.resolve(&t),
+						self.at()
+					));
+				}
+				Some(index - 1)
+			} else {
+				fields.iter().position(|(l, _)| l == label)
+			};
+			match at {
+				Some(i) if self.type_parameter(&fields[i]) => {
+					self.unify(&fields[i].1, arg)?;
+					given[i] = true;
+				}
+				Some(i) => {
+					self.parameter(&fields[i].1, arg)?;
+					if let Type::Type(declared) = self.resolve(&fields[i].1) {
+						fields[i].1 = *declared;
+					}
+					given[i] = true;
+				}
+				None => {
+					let field = self.field(&t, label)?;
+					self.unify(&field, arg)?;
+				}
+			}
+		}
+
+		// constructing a declared graph needs every typed point, type
+		// parameters are inferred from the values given, and giving
+		// types alone only instantiates it, `Pair(i32)`
+		let values = args
+			.iter()
+			.any(|(_, t)| !matches!(self.resolve(t), Type::Type(_)));
+		let constructor = values && self.declaration(scope, original);
+		for (i, (label, field)) in fields.iter().enumerate() {
+			let missing = !given[i]
+				&& !is_type_name(label)
+				&& matches!(field, Type::Type(_));
+			if constructor && missing {
+				return Err(format!(
+					"MissingArgument: {} for {} in {}",
+					label,
+					original_name(original),
+					self.at()
+				));
+			}
+		}
+
+		// a function-like graph evaluates to its return points
+		let results: Vec<usize> = (0..fields.len())
+			.filter(|i| fields[*i].0 == RESULT)
+			.collect();
+		if results.is_empty() {
+			return Ok(self.resolve(&Type::Graph(fields, tail)));
+		}
+		Ok(self.results(&fields, &results))
+	}
+
+	// labeled points of a graph argument, or positional values, the
+	// items of an array are spread over the parameters, `g[5, 6]`
+	fn arguments(
+		&mut self,
+		scope: Option<usize>,
+		argument: &AST,
+	) -> Result<Vec<(String, Type)>, String> {
+		if let AST::Array(items) = argument {
+			let mut args = vec![];
+			for item in items {
+				args.push((String::new(), self.expression(scope, item)?));
+			}
+			return Ok(args);
+		}
+		Ok(match self.expression(scope, argument)? {
+			Type::Graph(args, _) => args,
+			Type::Tuple(items) => {
+				items.into_iter().map(|t| (String::new(), t)).collect()
+			}
+			// an array of unknown length gives its first item
+			Type::Array(item) => vec![(String::new(), *item)],
+			t => vec![(String::new(), t)],
+		})
+	}
+
+	// an operator on graphs uses the operator key in scope whose
+	// parameters take the operands, `+: { a: b: Point -> ... }`
+	fn overload(
+		&mut self,
+		scope: Option<usize>,
+		op: Name,
+		operands: &[Type],
+	) -> Result<Option<Type>, String> {
+		let graphs = operands
+			.iter()
+			.any(|t| matches!(self.resolve(t), Type::Graph(_, _)));
+		if !graphs {
+			return Ok(None);
+		}
+		let mut matches = vec![];
+		for id in self.table.candidates(scope, symbol(op)) {
+			if self.pending.contains(&id) {
+				continue;
+			}
+			self.point(id)?;
+			let saved = (self.subst.clone(), self.levels.clone());
+			let fits = self.call(id, operands);
+			self.subst = saved.0;
+			self.levels = saved.1;
+			if let Ok(Some(_)) = fits {
+				matches.push(id);
+			}
+		}
+		match matches.len() {
+			0 => Ok(None),
+			1 => self.call(matches[0], operands),
+			_ => {
+				let operands: Vec<String> = operands
+					.iter()
+					.map(|t| self.resolve(t).to_string())
+					.collect();
+				let found: Vec<&str> = matches
+					.iter()
+					.map(|id| &self.table.points[*id].path[..])
+					.collect();
+				Err(format!(
+					"AmbiguousOperator: {} on {} matches {} in {}",
+					symbol(op),
+					operands.join(" and "),
+					found.join(" and "),
+					self.at()
+				))
+			}
+		}
+	}
+
+	// the result of a function-like graph given one value per parameter
+	fn call(
+		&mut self,
+		id: usize,
+		args: &[Type],
+	) -> Result<Option<Type>, String> {
+		let (params, result) = match self.signature(id) {
+			Some(signature) if signature.0.len() == args.len() => signature,
+			_ => return Ok(None),
+		};
+		let t = self.instance(id)?;
+		let fields = match self.resolve(&t) {
+			Type::Graph(fields, _) => fields,
+			_ => return Ok(None),
+		};
+		for (param, arg) in params.iter().zip(args) {
+			self.parameter(&fields[*param].1, arg)?;
+		}
+		Ok(Some(self.results(&fields, &result)))
+	}
+
+	// positions of the parameters and the return points of a
+	// function-like graph
+	fn signature(&self, id: usize) -> Option<(Vec<usize>, Vec<usize>)> {
+		let point = &self.table.points[id];
+		let graph = self.table.graph_of(point.scope, point.value)?;
+		let points = &self.table.scopes[graph].points;
+		let (results, params): (Vec<usize>, Vec<usize>) = (0..points.len())
+			.partition(|i| self.table.points[points[*i]].isreturn);
+		if results.is_empty() {
+			return None;
+		}
+		Some((params, results))
+	}
+
+	// one return point gives its value, several give a tuple
+	fn results(&self, fields: &[(String, Type)], results: &[usize]) -> Type {
+		let mut types: Vec<Type> =
+			results.iter().map(|i| self.resolve(&fields[*i].1)).collect();
+		match types.len() {
+			1 => types.remove(0),
+			_ => Type::Tuple(types),
+		}
+	}
+
+	// a parameter declared with a type takes values of that type, a
+	// typed number only values that widen into it
+	fn parameter(&mut self, field: &Type, arg: &Type) -> Result<(), String> {
+		match self.resolve(field) {
+			Type::Type(declared) => self.assign(&declared, arg),
+			Type::Number(n) if !number::is_literal(n) => self.assign(field, arg),
+			t @ Type::Range(_, _, _) => self.assign(&t, arg),
+			field => self.unify(&field, arg).map(|_| ()),
+		}
+	}
+
+	// a literal meeting a typed number must be one it holds,
+	// `u8 ~ 1000` or `u16(5) + 70000` are not
+	fn fits(&self, ast: &AST, t: &Type) -> Result<(), String> {
+		let n = match self.resolve(t) {
+			Type::Number(n) | Type::Range(n, _, _) => n,
+			_ => return Ok(()),
+		};
+		match number::integer(ast) {
+			Some(value) if !number::holds(n, value) => Err(format!(
+				"LiteralOutOfRange: {} outside {} in {}",
+				value,
+				number::name(n),
+				self.at()
+			)),
+			_ => Ok(()),
+		}
+	}
+
+	fn binary(&mut self, op: Name, l: &Type, r: &Type) -> Result<Type, String> {
+		let containers = self.container(l) || self.container(r);
+		match op {
+			Name::Add
+			| Name::Sub
+			| Name::Mul
+			| Name::Div
+			| Name::Exp
+			| Name::Gt
+			| Name::Ge
+			| Name::Lt
+			| Name::Le
+				if containers =>
+			{
+				self.elementwise(op, l, r)
+			}
+			Name::Add => {
+				let t = self.unify(l, r)?;
+				match self.resolve(&t) {
+					Type::String => Ok(t),
+					_ => self.numeric(op, &t),
+				}
+			}
+			Name::Sub | Name::Mul | Name::Div | Name::Exp => {
+				let t = self.unify(l, r)?;
+				self.numeric(op, &t)
+			}
+			Name::Gt | Name::Ge | Name::Lt | Name::Le => {
+				let t = self.unify(l, r)?;
+				self.numeric(op, &t)?;
+				Ok(Type::Boolean)
+			}
+			Name::Eq | Name::Ne => {
+				self.unify(l, r)?;
+				Ok(Type::Boolean)
+			}
+			// `u16 | Nothing` is the type of a value that may be missing
+			Name::Or if self.is_nothing(l) || self.is_nothing(r) => {
+				let t = if self.is_nothing(l) { r } else { l };
+				let t = self.declared(t)?;
+				Ok(Type::Type(Box::new(self.maybe(&t))))
+			}
+			Name::And | Name::Or => {
+				self.unify(l, &Type::Boolean)?;
+				self.unify(r, &Type::Boolean)?;
+				Ok(Type::Boolean)
+			}
+			Name::Range => {
+				let t = self.unify(l, r)?;
+				self.numeric(op, &t)?;
+				Ok(Type::Array(Box::new(t)))
+			}
+			_ => Err(format!("UnknownOperator: {:?} in {}", op, self.at())),
+		}
+	}
+
+	// arithmetic and ordering apply point by point to graphs of the
+	// same labels, arrays and tuples of the same shape, and a scalar on
+	// either side is paired with every element
+	fn elementwise(
+		&mut self,
+		op: Name,
+		l: &Type,
+		r: &Type,
+	) -> Result<Type, String> {
+		match (self.resolve(l), self.resolve(r)) {
+			(Type::Graph(xs, None), Type::Graph(ys, None)) => {
+				if xs.len() != ys.len() {
+					return Err(self.shape(op, l, r));
+				}
+				let mut fields = vec![];
+				for (i, (label, x)) in xs.iter().enumerate() {
+					let y = if positional(label) {
+						ys.get(i).filter(|(l, _)| l == label)
+					} else {
+						ys.iter().find(|(l, _)| l == label)
+					};
+					match y {
+						Some((_, y)) => {
+							fields.push((label.clone(), self.binary(op, x, y)?))
+						}
+						None => return Err(self.shape(op, l, r)),
+					}
+				}
+				Ok(Type::Graph(fields, None))
+			}
+			(Type::Graph(_, _), Type::Graph(_, _))
+			| (Type::Var(_), _)
+			| (_, Type::Var(_)) => {
+				// an unknown side takes the shape of the other
+				let t = self.unify(l, r)?;
+				self.broadcast(&t, |c, item| c.binary(op, item, item))
+			}
+			(Type::Array(x), Type::Array(y)) => {
+				Ok(Type::Array(Box::new(self.binary(op, &x, &y)?)))
+			}
+			(Type::Tuple(xs), Type::Tuple(ys)) => {
+				if xs.len() != ys.len() {
+					return Err(self.shape(op, l, r));
+				}
+				let mut items = vec![];
+				for (x, y) in xs.iter().zip(&ys) {
+					items.push(self.binary(op, x, y)?);
+				}
+				Ok(Type::Tuple(items))
+			}
+			(container, scalar) if !self.container(&scalar) => {
+				self.broadcast(&container, |c, item| c.binary(op, item, &scalar))
+			}
+			(scalar, container) if !self.container(&scalar) => {
+				self.broadcast(&container, |c, item| c.binary(op, &scalar, item))
+			}
+			_ => Err(self.shape(op, l, r)),
+		}
+	}
+
+	fn shape(&self, op: Name, l: &Type, r: &Type) -> String {
+		format!(
+			"ShapeMismatch: {} {} {} in {}",
+			self.resolve(l),
+			symbol(op),
+			self.resolve(r),
+			self.at()
+		)
+	}
+
+	fn broadcast<F>(&mut self, container: &Type, mut f: F) -> Result<Type, String>
+	where
+		F: FnMut(&mut Self, &Type) -> Result<Type, String>,
+	{
+		match self.resolve(container) {
+			Type::Array(item) => Ok(Type::Array(Box::new(f(self, &item)?))),
+			Type::Tuple(items) => {
+				let mut types = vec![];
+				for item in &items {
+					types.push(f(self, item)?);
+				}
+				Ok(Type::Tuple(types))
+			}
+			Type::Graph(fields, tail) => {
+				let mut types = vec![];
+				for (label, item) in &fields {
+					types.push((label.clone(), f(self, item)?));
+				}
+				Ok(Type::Graph(types, tail))
+			}
+			t => f(self, &t),
+		}
+	}
+
+	// the type Nothing, as opposed to a value that may be missing
+	fn is_nothing(&self, t: &Type) -> bool {
+		self.resolve(t) == Type::Type(Box::new(Type::Nothing))
+	}
+
+	fn container(&self, t: &Type) -> bool {
+		matches!(
+			self.resolve(t),
+			Type::Array(_) | Type::Tuple(_) | Type::Graph(_, _)
+		)
+	}
+
+	fn unary(&mut self, op: Name, t: &Type) -> Result<Type, String> {
+		match op {
+			Name::Add | Name::Sub if self.container(t) => {
+				self.broadcast(t, |c, item| c.unary(op, item))
+			}
+			Name::Add | Name::Sub => self.numeric(op, t),
+			Name::Not => self.unify(t, &Type::Boolean),
+			Name::Range => {
+				self.numeric(op, t)?;
+				Ok(Type::Array(Box::new(t.clone())))
+			}
+			Name::Length => match self.resolve(t) {
+				Type::Array(_)
+				| Type::Tuple(_)
+				| Type::Graph(_, _)
+				| Type::String => Ok(Type::Number(Number::Integer)),
+				other => Err(format!(
+					"InvalidOperand: {:?} on {} in {}",
+					op,
+					other,
+					self.at()
+				)),
+			},
+			// `<x` is prev and typed as a delay, `>x` and `:x` as a
+			// value have no meaning
+			Name::Colon | Name::Gt | Name::Lt => Err(format!(
+				"UnsupportedOperator: unary {} in {}",
+				symbol(op),
+				self.at()
+			)),
+			_ => Err(format!("UnknownOperator: {:?} in {}", op, self.at())),
+		}
+	}
+
+	// numbers only, unknowns are left to be fixed by later uses
+	fn numeric(&mut self, op: Name, t: &Type) -> Result<Type, String> {
+		match self.resolve(t) {
+			Type::Number(_) | Type::Range(_, _, _) | Type::Var(_) => {
+				Ok(t.clone())
+			}
+			Type::Maybe(_) => Err(self.nothing(t)),
+			other => Err(format!(
+				"InvalidOperand: {:?} on {} in {}",
+				op,
+				other,
+				self.at()
+			)),
+		}
+	}
+
+	fn fresh(&mut self) -> Type {
+		Type::Var(self.fresh_var())
+	}
+
+	fn fresh_row(&mut self) -> usize {
+		self.fresh_var()
+	}
+
+	fn fresh_var(&mut self) -> usize {
+		self.fresh_var_at(self.pending.len())
+	}
+
+	fn fresh_at(&mut self, level: usize) -> Type {
+		Type::Var(self.fresh_var_at(level))
+	}
+
+	fn fresh_var_at(&mut self, level: usize) -> usize {
+		self.subst.push(None);
+		self.levels.push(level);
+		self.subst.len() - 1
+	}
+
+	// a type with every bound variable replaced
+	fn resolve(&self, t: &Type) -> Type {
+		match t {
+			Type::Var(v) => match &self.subst[*v] {
+				Some(t) => self.resolve(t),
+				None => t.clone(),
+			},
+			Type::Array(item) => Type::Array(Box::new(self.resolve(item))),
+			Type::Maybe(t) => Type::Maybe(Box::new(self.resolve(t))),
+			Type::Stream(t) => Type::Stream(Box::new(self.resolve(t))),
+			Type::Type(t) => Type::Type(Box::new(self.resolve(t))),
+			Type::Tuple(items) => {
+				Type::Tuple(items.iter().map(|t| self.resolve(t)).collect())
+			}
+			Type::Graph(fields, tail) => {
+				let mut fields: Vec<(String, Type)> = fields
+					.iter()
+					.map(|(l, t)| (l.clone(), self.resolve(t)))
+					.collect();
+				let mut tail = *tail;
+				while let Some(row) = tail {
+					match &self.subst[row] {
+						Some(Type::Graph(more, next)) => {
+							for (l, t) in more {
+								fields.push((l.clone(), self.resolve(t)));
+							}
+							tail = *next;
+						}
+						_ => break,
+					}
+				}
+				Type::Graph(fields, tail)
+			}
+			Type::Union(cases) => Type::Union(
+				cases
+					.iter()
+					.map(|(n, t)| (n.clone(), self.resolve(t)))
+					.collect(),
+			),
+			_ => t.clone(),
+		}
+	}
+
+	// unbound variables of a type, row variables included
+	fn free(&self, t: &Type, out: &mut Vec<usize>) {
+		match self.resolve(t) {
+			Type::Var(v) if !out.contains(&v) => out.push(v),
+			Type::Array(item)
+			| Type::Maybe(item)
+			| Type::Stream(item)
+			| Type::Type(item) => self.free(&item, out),
+			Type::Tuple(items) => {
+				for t in &items {
+					self.free(t, out)
+				}
+			}
+			Type::Graph(fields, tail) => {
+				for (_, t) in &fields {
+					self.free(t, out)
+				}
+				if let Some(row) = tail {
+					if !out.contains(&row) {
+						out.push(row)
+					}
+				}
+			}
+			Type::Union(cases) => {
+				for (_, t) in &cases {
+					self.free(t, out)
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn bind(&mut self, v: usize, t: &Type) -> Result<(), String> {
+		let mut free = vec![];
+		self.free(t, &mut free);
+		if free.contains(&v) {
+			return Err(format!(
+				"InfiniteType: {} in {} in {}",
+				Type::Var(v),
+				self.resolve(t),
+				self.at()
+			));
+		}
+		// whatever a variable is bound to lives as long as it does
+		for w in free {
+			self.levels[w] = self.levels[w].min(self.levels[v]);
+		}
+		self.subst[v] = Some(t.clone());
+		Ok(())
+	}
+
+	fn unify(&mut self, a: &Type, b: &Type) -> Result<Type, String> {
+		let (a, b) = (self.resolve(a), self.resolve(b));
+		match (&a, &b) {
+			(Type::Var(x), Type::Var(y)) if x == y => Ok(a),
+			(Type::Var(v), t) | (t, Type::Var(v)) => {
+				self.bind(*v, t)?;
+				Ok(t.clone())
+			}
+			// bounds only matter where a value is annotated
+			(Type::Range(n, _, _), _) => self.unify(&Type::Number(*n), &b),
+			(_, Type::Range(n, _, _)) => self.unify(&a, &Type::Number(*n)),
+			(Type::Number(x), Type::Number(y)) => {
+				match number::join(*x, *y) {
+					Ok(n) => Ok(Type::Number(n)),
+					Err(msg) => Err(format!("{} in {}", msg, self.at())),
+				}
+			}
+			(Type::Array(x), Type::Array(y)) => {
+				Ok(Type::Array(Box::new(self.unify(x, y)?)))
+			}
+			(Type::Maybe(x), Type::Maybe(y)) => {
+				Ok(Type::Maybe(Box::new(self.unify(x, y)?)))
+			}
+			(Type::Stream(x), Type::Stream(y)) => {
+				Ok(Type::Stream(Box::new(self.unify(x, y)?)))
+			}
+			(Type::Maybe(_), Type::Nothing) => Ok(a),
+			(Type::Nothing, Type::Maybe(_)) => Ok(b),
+			(Type::Maybe(_), _) => Err(self.nothing(&a)),
+			(_, Type::Maybe(_)) => Err(self.nothing(&b)),
+			(Type::Type(x), Type::Type(y)) => {
+				Ok(Type::Type(Box::new(self.unify(x, y)?)))
+			}
+			(Type::Tuple(xs), Type::Tuple(ys)) if xs.len() == ys.len() => {
+				let mut items = vec![];
+				for (x, y) in xs.iter().zip(ys) {
+					items.push(self.unify(x, y)?);
+				}
+				Ok(Type::Tuple(items))
+			}
+			(Type::Graph(xs, xr), Type::Graph(ys, yr)) => {
+				self.unify_graph(xs, *xr, ys, *yr)?;
+				Ok(self.resolve(&a))
+			}
+			// unions are the same when their variants are
+			(Type::Union(xs), Type::Union(ys))
+				if xs.len() == ys.len()
+					&& xs.iter().zip(ys).all(|(x, y)| x.0 == y.0) =>
+			{
+				for (x, y) in xs.iter().zip(ys) {
+					self.unify(&x.1, &y.1)?;
+				}
+				Ok(self.resolve(&a))
+			}
+			_ if a == b => Ok(a),
+			_ => Err(self.mismatch(&a, &b)),
+		}
+	}
+
+	// labeled points meet by name, unlabeled ones by position, and
+	// points missing on one side must fit in that side's row
+	fn unify_graph(
+		&mut self,
+		xs: &[(String, Type)],
+		xr: Option<usize>,
+		ys: &[(String, Type)],
+		yr: Option<usize>,
+	) -> Result<(), String> {
+		let a = Type::Graph(xs.to_vec(), xr);
+		let b = Type::Graph(ys.to_vec(), yr);
+		for label in ["", RESULT] {
+			let xu: Vec<&Type> = unlabeled(xs, label);
+			let yu: Vec<&Type> = unlabeled(ys, label);
+			if xu.len() != yu.len() {
+				return Err(self.mismatch(&a, &b));
+			}
+			for (x, y) in xu.into_iter().zip(yu) {
+				self.unify(x, y)?;
+			}
+		}
+
+		let mut only_x = vec![];
+		for (label, x) in xs.iter().filter(|(l, _)| !positional(l)) {
+			match ys.iter().find(|(l, _)| l == label) {
+				Some((_, y)) => {
+					self.unify(x, y)?;
+				}
+				None => only_x.push((label.clone(), x.clone())),
+			}
+		}
+		let only_y: Vec<(String, Type)> = ys
+			.iter()
+			.filter(|(l, _)| {
+				!positional(l) && !xs.iter().any(|(k, _)| k == l)
+			})
+			.cloned()
+			.collect();
+
+		match (xr, yr) {
+			(None, None) if only_x.is_empty() && only_y.is_empty() => {
+				Ok(())
+			}
+			(Some(x), None) if only_x.is_empty() => {
+				self.bind(x, &Type::Graph(only_y, None))
+			}
+			(None, Some(y)) if only_y.is_empty() => {
+				self.bind(y, &Type::Graph(only_x, None))
+			}
+			(Some(x), Some(y)) if x == y => {
+				if only_x.is_empty() && only_y.is_empty() {
+					Ok(())
+				} else {
+					Err(self.mismatch(&a, &b))
+				}
+			}
+			(Some(x), Some(y)) => {
+				let rest = self.fresh_row();
+				self.levels[rest] = self.levels[x].min(self.levels[y]);
+				self.bind(x, &Type::Graph(only_y, Some(rest)))?;
+				self.bind(y, &Type::Graph(only_x, Some(rest)))
+			}
+			_ => Err(self.mismatch(&a, &b)),
+		}
+	}
+
+	fn mismatch(&self, a: &Type, b: &Type) -> String {
+		format!(
+			"TypeMismatch: expected {} found {} in {}",
+			self.resolve(a),
+			self.resolve(b),
+			self.at()
+		)
+	}
+
+	// the typed tree of an expression, after checking
+	fn build(&self, ast: &AST) -> Typed {
+		let of = match self.nodes.get(&(ast as *const AST)) {
+			Some(t) => self.resolve(t),
+			None => Type::Nothing,
+		};
+		let list = |items: &Vec<AST>| -> Vec<Typed> {
+			items.iter().map(|item| self.build(item)).collect()
+		};
+		let node = match ast {
+			AST::Nothing => Node::Nothing,
+			AST::Number(n, text) => Node::Number(*n, text.clone()),
+			AST::String(text) => Node::String(text.clone()),
+			AST::Graph(items) => Node::Graph(list(items)),
+			AST::Array(items) => Node::Array(list(items)),
+			AST::Tuple(items) => Node::Tuple(list(items)),
+			AST::Point(label, index, isreturn, value) => {
+				let value = self.build(value);
+				let of = match self.table.point(ast) {
+					Some(id) => match &self.types[id] {
+						Some(t) => self.resolve(t),
+						None => value.of.clone(),
+					},
+					None => value.of.clone(),
+				};
+				return Typed {
+					of,
+					node: Node::Point(
+						label.clone(),
+						*index,
+						*isreturn,
+						Box::new(value),
+					),
+				};
+			}
+			AST::Op2(op, left, right) => Node::Op2(
+				*op,
+				Box::new(self.build(left)),
+				Box::new(self.build(right)),
+			),
+			AST::Op1(op, value) => {
+				Node::Op1(*op, Box::new(self.build(value)))
+			}
+			AST::Ref(name) => Node::Ref(name.clone()),
+			AST::Arg(value) => Node::Arg(Box::new(self.build(value))),
+			AST::Rep(original, argument) => Node::Rep(
+				Box::new(self.build(original)),
+				Box::new(self.build(argument)),
+			),
+		};
+		Typed { of, node }
+	}
+
+	// path of the point being checked, for error messages
+	fn at(&self) -> &str {
+		match self.pending.last() {
+			Some(id) => &self.table.points[*id].path,
+			None => "",
+		}
+	}
+}
+
+fn original_name(ast: &AST) -> &str {
+	match ast {
+		AST::Ref(name) => name,
+		AST::Op2(Name::Select, _, right) => original_name(right),
+		AST::Rep(original, _) => original_name(original),
+		_ => "graph",
+	}
+}
+
+// type names available everywhere
+pub fn builtin(name: &str) -> Option<Type> {
+	match name {
+		"Nothing" => Some(Type::Nothing),
+		"Boolean" => Some(Type::Boolean),
+		"String" => Some(Type::String),
+		"Integer" => Some(Type::Number(Number::Integer)),
+		"Decimal" => Some(Type::Number(Number::Decimal)),
+		_ => number::from_name(name).map(Type::Number),
+	}
+}
+
+// unlabeled points and return points are matched by position
+fn positional(label: &str) -> bool {
+	label.is_empty() || label == RESULT
+}
+
+fn unlabeled<'a>(fields: &'a [(String, Type)], label: &str) -> Vec<&'a Type> {
+	fields
+		.iter()
+		.filter(|(l, _)| l == label)
+		.map(|(_, t)| t)
+		.collect()
+}
+
+impl fmt::Display for Type {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Type::Nothing => write!(f, "Nothing"),
+			Type::Boolean => write!(f, "Boolean"),
+			Type::Number(n) => write!(f, "{}", number::name(*n)),
+			Type::Range(_, lo, hi) => write!(f, "{}..{}", lo, hi),
+			Type::String => write!(f, "String"),
+			Type::Array(item) => write!(f, "[{}]", item),
+			Type::Maybe(t) => write!(f, "{} | Nothing", t),
+			Type::Stream(t) => write!(f, "Stream({})", t),
+			Type::Type(t) => write!(f, "Type({})", t),
+			Type::Tuple(items) => {
+				let items: Vec<String> =
+					items.iter().map(|t| t.to_string()).collect();
+				write!(f, "({})", items.join(", "))
+			}
+			Type::Graph(fields, tail) => {
+				let (results, fields): (Vec<_>, Vec<_>) =
+					fields.iter().partition(|(l, _)| l == RESULT);
+				let mut fields: Vec<String> = fields
+					.iter()
+					.map(|(label, t)| {
+						if label.is_empty() {
+							t.to_string()
+						} else {
+							format!("{}: {}", label, t)
+						}
+					})
+					.collect();
+				if tail.is_some() {
+					fields.push("..".to_string());
+				}
+				let mut text = fields.join(", ");
+				if !results.is_empty() {
+					let results: Vec<String> =
+						results.iter().map(|(_, t)| t.to_string()).collect();
+					if !text.is_empty() {
+						text.push(' ');
+					}
+					text.push_str(&format!("-> {}", results.join(", ")));
+				}
+				write!(f, "{{{}}}", text)
+			}
+			Type::Union(cases) => {
+				let cases: Vec<String> = cases
+					.iter()
+					.map(|(name, payload)| match payload {
+						Type::Graph(fields, None) if fields.is_empty() => {
+							name.clone()
+						}
+						payload => format!("{}{}", name, payload),
+					})
+					.collect();
+				write!(f, "{}", cases.join(" | "))
+			}
+			// 'a to 'z, then 'a1..
+			Type::Var(v) => {
+				let letter = (b'a' + (v % 26) as u8) as char;
+				match v / 26 {
+					0 => write!(f, "'{}", letter),
+					n => write!(f, "'{}{}", letter, n),
+				}
+			}
+		}
+	}
+}
+
+impl Typed {
+	// renumbers the variables of the tree in order of appearance from
+	// those `names` already has
+	fn name(&mut self, names: &mut Vec<usize>) {
+		self.of = named(&self.of, names);
+		match &mut self.node {
+			Node::Graph(items) | Node::Array(items) | Node::Tuple(items) => {
+				for item in items {
+					item.name(names);
+				}
+			}
+			Node::Point(_, _, _, value) | Node::Op1(_, value) | Node::Arg(value) => {
+				value.name(names)
+			}
+			Node::Op2(_, left, right) | Node::Rep(left, right) => {
+				left.name(names);
+				right.name(names);
+			}
+			Node::Nothing | Node::Number(_, _) | Node::String(_) | Node::Ref(_) => {}
+		}
+	}
+}
+
+// a type with each variable numbered by where it is in `names`
+fn named(t: &Type, names: &mut Vec<usize>) -> Type {
+	let list = |items: &[Type], names: &mut Vec<usize>| -> Vec<Type> {
+		items.iter().map(|t| named(t, names)).collect()
+	};
+	let fields = |fields: &[(String, Type)], names: &mut Vec<usize>| {
+		fields
+			.iter()
+			.map(|(l, t)| (l.clone(), named(t, names)))
+			.collect::<Vec<_>>()
+	};
+	match t {
+		Type::Var(v) => match names.iter().position(|n| n == v) {
+			Some(i) => Type::Var(i),
+			None => {
+				names.push(*v);
+				Type::Var(names.len() - 1)
+			}
+		},
+		Type::Array(t) => Type::Array(Box::new(named(t, names))),
+		Type::Maybe(t) => Type::Maybe(Box::new(named(t, names))),
+		Type::Stream(t) => Type::Stream(Box::new(named(t, names))),
+		Type::Type(t) => Type::Type(Box::new(named(t, names))),
+		Type::Tuple(items) => Type::Tuple(list(items, names)),
+		Type::Graph(items, tail) => Type::Graph(fields(items, names), *tail),
+		Type::Union(cases) => Type::Union(fields(cases, names)),
+		t => t.clone(),
+	}
+}
+
+impl fmt::Display for Typed {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self.node {
+			Node::Nothing => Ok(()),
+			Node::Number(_, text) | Node::String(text) | Node::Ref(text) => {
+				write!(f, "{}", text)
+			}
+			Node::Graph(items) => write!(f, "{{{}}}", join(items)),
+			Node::Array(items) => write!(f, "[{}]", join(items)),
+			Node::Tuple(items) => write!(f, "({})", join(items)),
+			Node::Point(label, _, isreturn, value) => {
+				if *isreturn {
+					write!(f, "-> ")?;
+				}
+				// a parameter is written `x:` as in the source
+				match (label.is_empty(), &value.node) {
+					(false, Node::Nothing) => write!(f, "{}:", label),
+					(false, _) => write!(f, "{}: {}", label, value),
+					(true, _) => write!(f, "{}", value),
+				}
+			}
+			Node::Op2(Name::Select, left, right) => {
+				write!(f, "{}.{}", operand(left, Precedence::Select, false), right)
+			}
+			Node::Op2(Name::Optional, left, right) => {
+				write!(f, "{}?.{}", operand(left, Precedence::Select, false), right)
+			}
+			Node::Op2(Name::Range, left, right) => write!(
+				f,
+				"{}..{}",
+				operand(left, Precedence::Range, false),
+				operand(right, Precedence::Range, true)
+			),
+			Node::Op2(op, left, right) => {
+				let p = precedence(&self.node);
+				write!(
+					f,
+					"{} {} {}",
+					operand(left, p, false),
+					symbol(*op),
+					operand(right, p, true)
+				)
+			}
+			Node::Op1(op, value) => {
+				write!(f, "{}{}", symbol(*op), operand(value, Precedence::Unary, false))
+			}
+			Node::Arg(value) => write!(f, "{}", value),
+			Node::Rep(original, argument) => match argument.node {
+				Node::Graph(_) => write!(f, "{} {}", original, argument),
+				Node::Array(_) | Node::Tuple(_) => {
+					write!(f, "{}{}", original, argument)
+				}
+				_ => write!(f, "{}({})", original, argument),
+			},
+		}
+	}
+}
+
+fn join(items: &[Typed]) -> String {
+	let items: Vec<String> = items.iter().map(|t| t.to_string()).collect();
+	items.join(", ")
+}
+
+// wrap a nested operation in parens when it binds looser than its parent
+fn operand(t: &Typed, parent: Precedence, right: bool) -> String {
+	let p = precedence(&t.node);
+	if p < parent || (right && p == parent) {
+		format!("({})", t)
+	} else {
+		t.to_string()
+	}
+}
+
+fn precedence(node: &Node) -> Precedence {
+	match node {
+		Node::Op2(op, _, _) => parser::precedence(*op),
+		Node::Op1(_, _) => Precedence::Unary,
+		Node::Rep(_, _) => Precedence::Replicate,
+		_ => Precedence::Primary,
+	}
+}
+
+pub fn symbol(name: Name) -> &'static str {
+	match name {
+		Name::Pattern => "~",
+		Name::Coalesce => "??",
+		Name::Or => "|",
+		Name::And => "&",
+		Name::Add => "+",
+		Name::Sub => "-",
+		Name::Mul => "*",
+		Name::Div => "/",
+		Name::Exp => "^",
+		Name::Not => "!",
+		Name::Eq => "=",
+		Name::Ne => "!=",
+		Name::Gt => ">",
+		Name::Lt => "<",
+		Name::Ge => ">=",
+		Name::Le => "<=",
+		Name::Colon => ":",
+		Name::Length => "#",
+		Name::Select => ".",
+		Name::Optional => "?.",
+		Name::Range => "..",
+		Name::Arrow => "->",
+		_ => "?",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compiler::{parser, tokenizer};
+
+	// the type of the point at `path`, or the error checking gives
+	fn type_of(source: &str, path: &str) -> Result<String, String> {
+		let ast = parser::parser(&tokenizer::tokenizer(source))?;
+		let table = scope(&ast)?;
+		let types = types(&ast)?;
+		let id = table.points.iter().position(|p| p.path == path).unwrap();
+		Ok(types[id].to_string())
+	}
+
+	// the label, type and printed value of each point of the program
+	fn typed_points(source: &str) -> Vec<(String, String, String)> {
+		let ast = parser::parser(&tokenizer::tokenizer(source)).unwrap();
+		let typed = typer(&ast).unwrap();
+		let Node::Point(_, _, _, program) = typed.node else {
+			panic!("the program is no point");
+		};
+		let Node::Graph(items) = program.node else {
+			panic!("the program holds no graph");
+		};
+		items
+			.iter()
+			.map(|item| match &item.node {
+				Node::Point(label, _, _, value) => {
+					(label.clone(), item.of.to_string(), value.to_string())
+				}
+				_ => panic!("{} is no point", item),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn typed_tree_prints_as_written() {
+		let source = "xs: [1, 2]\n\
+			d: map{xs, {x: -> x * 2}}\n\
+			twice: {f: v: -> f{x: v}}";
+		let points = typed_points(source);
+		assert_eq!(points[0].0, "xs");
+		assert_eq!(points[0].1, "[Integer]");
+		assert_eq!(points[0].2, "[1, 2]");
+		assert_eq!(points[1].1, "[Integer]");
+		assert_eq!(points[1].2, "map {xs, {x:, -> x * 2}}");
+		assert_eq!(points[2].0, "twice");
+		assert_eq!(points[2].1, "{f: {x: 'b, .. -> 'a}, v: 'b -> 'a}");
+		assert_eq!(points[2].2, "{f:, v:, -> f {x: v}}");
+	}
+
+	#[test]
+	fn arguments_only_widen_into_typed_parameters() {
+		let narrowing = "f: { x: i8 ~ 0, -> x }\ny: f{ x: i64(500) }";
+		assert!(type_of(narrowing, "y").is_err());
+		let widening = "f: { x: i64 ~ 0, -> x }\ny: f{ x: i8(50) }";
+		assert_eq!(type_of(widening, "y"), Ok("i64".to_string()));
+		let literal = "g: { x: 0, -> x * 2 }\nh: g{ x: u8(3) }";
+		assert!(type_of(literal, "h").is_ok());
+	}
+
+	#[test]
+	fn named_construction_is_no_declaration() {
+		let point = "Point {a: i32, b: i32}\n";
+		let call = format!(
+			"{}f: {{p: Point, -> p.a + p.b}}\ny: f{{Point{{a: 1, b: 2}}}}",
+			point
+		);
+		assert_eq!(type_of(&call, "y"), Ok("i32".to_string()));
+		let listed = format!(
+			"{}xs: {{Point{{a: 1, b: 2}}, Point{{a: 3, b: 4}}}}\nz: xs.1.b",
+			point
+		);
+		assert_eq!(type_of(&listed, "z"), Ok("i32".to_string()));
+		let wrong = format!("{}z: Point{{a: 1.5, b: 2}}", point);
+		assert!(type_of(&wrong, "z").is_err());
+	}
+
+	#[test]
+	fn unions_construct_and_match_variants() {
+		let shape = "Shape: Circle{r: f64} | Rect{w: f64, h: f64}\n";
+		let area = format!(
+			"{}a: Rect{{w: 2, h: 3}}\n\
+			m: a ~ {{Circle: {{r: -> r * r}}, Rect: {{w: h: -> w * h}}}}",
+			shape
+		);
+		let union = "Circle{r: f64} | Rect{w: f64, h: f64}";
+		assert_eq!(type_of(&area, "a"), Ok(union.to_string()));
+		assert_eq!(type_of(&area, "m"), Ok("f64".to_string()));
+		let partial =
+			format!("{}a: Circle{{2}}\nm: a ~ {{Circle: 1}}", shape);
+		let err = type_of(&partial, "m").unwrap_err();
+		assert!(err.starts_with("NonExhaustive"), "{}", err);
+		let unknown = format!(
+			"{}a: Circle{{2}}\nm: a ~ {{Circle: 1, Rect: 2, Square: 3}}",
+			shape
+		);
+		let err = type_of(&unknown, "m").unwrap_err();
+		assert!(err.starts_with("UnknownVariant"), "{}", err);
+	}
+
+	#[test]
+	fn missing_values_need_a_default() {
+		let pos = "Pos: {x: f64, y: f64}\n\
+			f: {p: Pos | Nothing -> p?.x ?? 0.5}\n\
+			g: {x: 1}\n\
+			c: g?.x ?? 7";
+		let f = "{p: Type({x: f64, y: f64} | Nothing) -> f64}";
+		assert_eq!(type_of(pos, "f"), Ok(f.to_string()));
+		assert_eq!(type_of(pos, "c"), Ok("Integer".to_string()));
+		let missing = "r: Nothing\nd: r ?? 3";
+		assert_eq!(type_of(missing, "d"), Ok("Integer".to_string()));
+		let unchecked = "f: {p: {x: 0} | Nothing -> p.x}";
+		let err = type_of(unchecked, "f").unwrap_err();
+		assert!(err.starts_with("PossiblyNothing"), "{}", err);
+	}
+
+	#[test]
+	fn ranges_bound_the_literals_they_type() {
+		let percent = "Percent: 0..100\nq: Percent ~ 5";
+		assert_eq!(type_of(percent, "q"), Ok("0..100".to_string()));
+		let half = "s: -1.5..1.5 ~ 0.5";
+		assert_eq!(type_of(half, "s"), Ok("-1.5..1.5".to_string()));
+		let err = type_of("v: 0..100 ~ 140", "v").unwrap_err();
+		assert!(err.starts_with("RangeViolation"), "{}", err);
+		let err = type_of("v: 0..100 ~ \"x\"", "v").unwrap_err();
+		assert!(err.starts_with("AnnotationMismatch"), "{}", err);
+	}
+
+	#[test]
+	fn templates_take_each_type_given() {
+		let pair = "Pair: {T:, first: T, second: T}\n\
+			p: Pair(f64){1, 2}\n\
+			q: Pair(String){\"a\", \"b\"}";
+		let p = "{T: Type(f64), first: f64, second: f64}";
+		assert_eq!(type_of(pair, "p"), Ok(p.to_string()));
+		let q = "{T: Type(String), first: String, second: String}";
+		assert_eq!(type_of(pair, "q"), Ok(q.to_string()));
+		let mixed = "Pair: {T:, first: T, second: T}\nd: Pair{1, \"x\"}";
+		let err = type_of(mixed, "d").unwrap_err();
+		assert!(err.starts_with("AnnotationMismatch"), "{}", err);
+	}
+
+	#[test]
+	fn capitalized_values_are_not_types() {
+		assert_eq!(type_of("Pi: 3.14\nx: Pi", "x"), Ok("Decimal".to_string()));
+		let param = "Point: {x: f64}\nb: Point";
+		assert_eq!(type_of(param, "b"), Ok("Type({x: f64})".to_string()));
+	}
+
+	#[test]
+	fn annotations_check_the_expression() {
+		assert_eq!(type_of("a: i32 ~ 1 + 2", "a"), Ok("i32".to_string()));
+		let err = type_of("a: u8 ~ 300", "a").unwrap_err();
+		assert!(err.starts_with("LiteralOutOfRange"), "{}", err);
+		let err = type_of("a: String ~ 1", "a").unwrap_err();
+		assert!(err.starts_with("AnnotationMismatch"), "{}", err);
+	}
+
+	#[test]
+	fn operators_resolve_to_operator_keys() {
+		let point = "Point: {a: i32, b: i32}\n+: {l: r: Point -> l.a}\n";
+		let matched =
+			format!("{}o: Point{{a: 1, b: 2}} + Point{{a: 3, b: 4}}", point);
+		assert_eq!(type_of(&matched, "o"), Ok("i32".to_string()));
+		let numbers = format!("{}n: 1 + 2.5\nm: i32(1) + 2", point);
+		assert_eq!(type_of(&numbers, "n"), Ok("Decimal".to_string()));
+		assert_eq!(type_of(&numbers, "m"), Ok("i32".to_string()));
+		let ambiguous = format!(
+			"{}g: {{+: {{l: r: Point -> r.b}}, \
+			-> Point{{a: 1, b: 2}} + Point{{a: 3, b: 4}}}}",
+			point
+		);
+		let err = type_of(&ambiguous, "g").unwrap_err();
+		assert!(err.starts_with("AmbiguousOperator"), "{}", err);
+	}
+
+	#[test]
+	fn operators_apply_element_wise() {
+		let graphs = "g: {a: 1, b: 2} + {a: 3, b: 4}";
+		assert_eq!(
+			type_of(graphs, "g"),
+			Ok("{a: Integer, b: Integer}".to_string())
+		);
+		let scaled = "g: [1, 2] * 2";
+		assert_eq!(type_of(scaled, "g"), Ok("[Integer]".to_string()));
+		let compared = "g: [1, 2] < [3, 4]";
+		assert_eq!(type_of(compared, "g"), Ok("[Boolean]".to_string()));
+		let err = type_of("g: {a: 1} + {b: 2}", "g").unwrap_err();
+		assert!(err.starts_with("ShapeMismatch"), "{}", err);
+	}
+
+	#[test]
+	fn literals_fit_the_number_they_meet() {
+		let err = type_of("z: u8 ~ 1000", "z").unwrap_err();
+		assert!(err.starts_with("LiteralOutOfRange"), "{}", err);
+		assert!(type_of("z: u8 ~ 255", "z").is_ok());
+		assert!(type_of("z: i8 ~ -129", "z").is_err());
+		assert!(type_of("z: u16(5) + 70000", "z").is_err());
+		assert_eq!(type_of("z: u16(5) + 7000", "z"), Ok("u16".to_string()));
+	}
+
+	#[test]
+	fn arrays_spread_over_parameters() {
+		let g = "g: { a: 0, b: 0, -> a + b }\n";
+		let x = format!("{}x: g[5]", g);
+		assert_eq!(type_of(&x, "x"), Ok("Integer".to_string()));
+		let y = format!("{}y: g[5, 6]", g);
+		assert_eq!(type_of(&y, "y"), Ok("Integer".to_string()));
+		let z = format!("{}z: g[5, 6, 7]", g);
+		assert!(type_of(&z, "z").is_err());
+	}
+
+	#[test]
+	fn functions_pass_as_parameters() {
+		let source = "twice: { f: v: -> f{x: f{x: v}} }\n\
+			inc: { x: 0, -> x + 1 }\n\
+			z: twice{ f: inc, v: 3 }";
+		assert_eq!(type_of(source, "z"), Ok("Integer".to_string()));
+	}
+
+	#[test]
+	fn only_prev_is_a_unary_tick_operator() {
+		let err = type_of("a: 1\nb: >a", "b").unwrap_err();
+		assert!(err.starts_with("UnsupportedOperator"), "{}", err);
+		assert!(type_of("a: 1\nb: :a + 1", "b").is_err());
+		assert_eq!(type_of("a: 1\nb: <a", "b"), Ok("Integer | Nothing".to_string()));
+	}
 }
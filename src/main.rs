@@ -2,8 +2,11 @@ use std::env;
 use std::fs;
 
 pub mod compiler;
-use compiler::{parser, tokenizer, typer};
+use compiler::{bytecode, dependency, parser, reducer, tokenizer, typer};
+use dependency::Dependencies;
+use typer::{Node, Typed};
 use parser::AST;
+use reducer::Value;
 use tokenizer::Token;
 
 // use notify::{raw_watcher, RawEvent, RecursiveMode, Watcher};
@@ -52,48 +55,78 @@ fn main() {
 	// }
 }
 
-fn event_router(operation: notify::Op, source: &String, target: &String) {
-	match operation {
-		notify::op::WRITE => {
-			let input = read_file(source);
-			let tokens = tokenizer::tokenizer(&input);
-			let token_path = &mut target.clone();
-			token_path.push_str(&".tokens".to_string());
-			write_file(token_path, &token_string(&tokens));
+fn event_router(operation: notify::Op, source: &str, target: &str) {
+	if operation != notify::op::WRITE {
+		return;
+	}
+
+	let input = read_file(source);
+	let tokens = tokenizer::tokenizer(&input);
+	let token_path = format!("{}.tokens", target);
+	write_file(&token_path, &token_string(&tokens));
+	//
+	//
+	let parse_path = format!("{}.ast", target);
+	match parser::parser(&tokens) {
+		Ok(parse) => {
+			write_file(&parse_path, &ast_string(&parse));
+
+			//
+			//
+			let deps_path = format!("{}.deps", target);
+			match dependency::dependencies(&parse) {
+				Ok(deps) => {
+					write_file(&deps_path, &deps_string(&deps));
+				}
+				Err(msg) => {
+					write_file(&deps_path, &msg);
+				}
+			}
+
 			//
 			//
-			let parse_path = &mut target.clone();
-			parse_path.push_str(&".ast".to_string());
-			match parser::parser(&tokens) {
-				Ok(parse) => {
-					write_file(parse_path, &ast_string(&parse));
-
-					//
-					//
-					let typed_parse_path = &mut target.clone();
-					typed_parse_path.push_str(&".typed".to_string());
-					match typer::typer(&parse) {
-						Ok(typed_parse) => {
-							write_file(
-								typed_parse_path,
-								&ast_string(&typed_parse),
-							);
+			let typed_parse_path = format!("{}.typed", target);
+			match typer::typer(&parse) {
+				Ok(typed_parse) => {
+					write_file(&typed_parse_path, &typed_string(&typed_parse));
+				}
+				Err(msg) => {
+					write_file(&typed_parse_path, &msg);
+				}
+			}
+
+			//
+			//
+			let values_path = format!("{}.values", target);
+			let code_path = format!("{}.code", target);
+			match reducer::Reducer::new(&parse) {
+				Ok(mut r) => {
+					match r.point(0) {
+						Ok(value) => {
+							write_file(&values_path, &values_string(&value));
 						}
 						Err(msg) => {
-							write_file(typed_parse_path, &msg);
+							write_file(&values_path, &msg);
 						}
 					}
+					write_file(
+						&code_path,
+						&bytecode::disassemble(&r.deps.table, &r.code),
+					);
 				}
 				Err(msg) => {
-					write_file(parse_path, &msg);
+					write_file(&values_path, &msg);
+					write_file(&code_path, &msg);
 				}
 			}
 		}
-		_ => {}
-	};
+		Err(msg) => {
+			write_file(&parse_path, &msg);
+		}
+	}
 }
 
-fn read_file(path: &String) -> String {
+fn read_file(path: &str) -> String {
 	match fs::read_to_string(path) {
 		Ok(v) => v,
 		Err(e) => {
@@ -106,7 +139,7 @@ fn read_file(path: &String) -> String {
 	}
 }
 
-fn write_file(path: &String, data: &String) {
+fn write_file(path: &str, data: &str) {
 	match fs::write(path, data) {
 		Ok(_v) => {
 			// dbg!(v);
@@ -118,7 +151,7 @@ fn write_file(path: &String, data: &String) {
 	};
 }
 
-fn token_string(data: &Vec<Token>) -> String {
+fn token_string(data: &[Token]) -> String {
 	let mut output = String::new();
 	for group in data {
 		output.push_str(
@@ -137,3 +170,105 @@ fn token_string(data: &Vec<Token>) -> String {
 fn ast_string(data: &AST) -> String {
 	format!("{:#?}", data)
 }
+
+fn deps_string(data: &Dependencies) -> String {
+	let mut output = String::new();
+	for id in &data.order {
+		// reads through prev are marked `<`
+		let mut reads: Vec<String> = data.edges[*id]
+			.iter()
+			.map(|point| data.table.points[*point].path.clone())
+			.collect();
+		reads.extend(
+			data.delayed[*id]
+				.iter()
+				.map(|point| format!("<{}", data.table.points[*point].path)),
+		);
+		output.push_str(
+			&format!(
+				"{:<24} <- {}\n",
+				data.table.points[*id].path,
+				reads.join(", ")
+			)[..],
+		)
+	}
+	output
+}
+
+fn typed_string(data: &Typed) -> String {
+	let mut output = String::new();
+	match &data.node {
+		Node::Point(_, _, _, value) => match &value.node {
+			Node::Graph(items) => typed_points(&mut output, items, 0),
+			_ => typed_point(&mut output, data, 0),
+		},
+		_ => output.push_str(&format!("{}: {}\n", data, data.of)[..]),
+	}
+	output
+}
+
+fn typed_points(output: &mut String, items: &[Typed], depth: usize) {
+	for item in items {
+		typed_point(output, item, depth);
+	}
+}
+
+// `label: Type = expr`, graph literals are expanded point by point
+fn typed_point(output: &mut String, data: &Typed, depth: usize) {
+	let indent = "\t".repeat(depth);
+	match &data.node {
+		Node::Point(label, _, isreturn, value) => {
+			let arrow = if *isreturn { "-> " } else { "" };
+			let head = match (label.is_empty(), &value.node) {
+				(false, Node::Graph(_)) => format!("{}{}:", arrow, label),
+				(true, Node::Graph(_)) => arrow.to_string(),
+				(false, _) => format!("{}{}: {}", arrow, label, data.of),
+				(true, _) => format!("{}{}", arrow, data.of),
+			};
+			match &value.node {
+				Node::Graph(items) => {
+					output.push_str(&format!("{}{} {{\n", indent, head)[..]);
+					typed_points(output, items, depth + 1);
+					output.push_str(&format!("{}}}\n", indent)[..]);
+				}
+				Node::Nothing => {
+					output.push_str(&format!("{}{}\n", indent, head)[..])
+				}
+				_ => output.push_str(
+					&format!("{}{} = {}\n", indent, head, value)[..],
+				),
+			}
+		}
+		_ => output.push_str(&format!("{}{}\n", indent, data)[..]),
+	}
+}
+
+fn values_string(data: &Value) -> String {
+	let mut output = String::new();
+	match data {
+		Value::Graph(graph) => value_points(&mut output, &graph.points, 0),
+		_ => output.push_str(&format!("{}\n", data)[..]),
+	}
+	output
+}
+
+// `label: value`, graph values are expanded point by point
+fn value_points(output: &mut String, points: &[(String, Value)], depth: usize) {
+	let indent = "\t".repeat(depth);
+	for (label, value) in points {
+		let head = if label.is_empty() {
+			String::new()
+		} else {
+			format!("{}: ", label)
+		};
+		match value {
+			Value::Graph(graph) => {
+				output.push_str(&format!("{}{}{{\n", indent, head)[..]);
+				value_points(output, &graph.points, depth + 1);
+				output.push_str(&format!("{}}}\n", indent)[..]);
+			}
+			_ => output
+				.push_str(&format!("{}{}{}\n", indent, head, value)[..]),
+		}
+	}
+}